@@ -2,9 +2,12 @@ set -euxo pipefail
 
 main() {
     cargo check --target $TARGET
+    cargo check --target $TARGET --features defmt
+    cargo check --target $TARGET --features embedded-hal
 
     if [ $TARGET = x86_64-unknown-linux-gnu ]; then
         cargo test --target $TARGET
+        cargo test --target $TARGET --features embedded-hal
         cargo check --target $TARGET --features std
         return
     fi
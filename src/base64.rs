@@ -0,0 +1,188 @@
+//! Base64 (RFC 4648 standard alphabet, with padding) encoding and decoding
+//!
+//! Characters are mapped with arithmetic rather than lookup tables to save ROM / Flash
+
+/// Encodes a stream of bytes, 3 bytes at a time
+#[derive(Clone, Copy)]
+pub(crate) struct Encoder {
+    pending: [u8; 2],
+    len: usize,
+}
+
+impl Encoder {
+    pub(crate) fn new() -> Self {
+        Encoder {
+            pending: [0; 2],
+            len: 0,
+        }
+    }
+
+    /// Adds `byte`; returns the next 4 characters once 3 bytes have been added
+    pub(crate) fn push(&mut self, byte: u8) -> Option<[u8; 4]> {
+        if self.len < 2 {
+            self.pending[self.len] = byte;
+            self.len += 1;
+            return None;
+        }
+
+        self.len = 0;
+        Some(encode([self.pending[0], self.pending[1], byte], 3))
+    }
+
+    /// Returns the padded characters of the bytes added since the last 4 returned characters
+    pub(crate) fn finish(self) -> Option<[u8; 4]> {
+        match self.len {
+            0 => None,
+            1 => Some(encode([self.pending[0], 0, 0], 1)),
+            len => Some(encode([self.pending[0], self.pending[1], 0], len)),
+        }
+    }
+}
+
+/// Decodes base64 text, 4 characters at a time
+pub(crate) struct Decoder<'a> {
+    input: &'a [u8],
+    bytes: [u8; 3],
+    len: usize,
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(input: &'a [u8]) -> Self {
+        Decoder {
+            input,
+            bytes: [0; 3],
+            len: 0,
+            pos: 0,
+        }
+    }
+
+    /// Returns the next byte, `Ok(None)` at the end of the input or `Err` if the input is not
+    /// valid base64
+    pub(crate) fn next(&mut self) -> Result<Option<u8>, ()> {
+        if self.pos == self.len {
+            if self.input.is_empty() {
+                return Ok(None);
+            }
+
+            self.decode_quantum()?;
+        }
+
+        self.pos += 1;
+        Ok(Some(self.bytes[self.pos - 1]))
+    }
+
+    fn decode_quantum(&mut self) -> Result<(), ()> {
+        if self.input.len() < 4 {
+            return Err(());
+        }
+
+        let (quantum, rest) = self.input.split_at(4);
+        let padding = match (quantum[2], quantum[3]) {
+            (b'=', b'=') => 2,
+            (_, b'=') => 1,
+            _ => 0,
+        };
+        // padding is only allowed at the very end
+        if padding != 0 && !rest.is_empty() {
+            return Err(());
+        }
+
+        let mut n = 0u32;
+        for c in &quantum[..4 - padding] {
+            n = n << 6 | decode_char(*c).ok_or(())? as u32;
+        }
+        n <<= 6 * padding as u32;
+
+        self.bytes = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+        self.len = 3 - padding;
+        self.pos = 0;
+        self.input = rest;
+        Ok(())
+    }
+}
+
+/// Encodes the first `len` bytes of `bytes`, padding the output
+fn encode(bytes: [u8; 3], len: usize) -> [u8; 4] {
+    let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+    let mut chars = [b'='; 4];
+    for (i, c) in chars.iter_mut().enumerate().take(len + 1) {
+        *c = encode_char((n >> (18 - 6 * i)) as u8 & 0x3f);
+    }
+    chars
+}
+
+fn encode_char(v: u8) -> u8 {
+    match v {
+        0..=25 => b'A' + v,
+        26..=51 => b'a' + (v - 26),
+        52..=61 => b'0' + (v - 52),
+        62 => b'+',
+        _ => b'/',
+    }
+}
+
+fn decode_char(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decoder, Encoder};
+
+    #[test]
+    fn round_trip() {
+        // RFC 4648 test vectors
+        for &(bytes, text) in &[
+            (&b""[..], &b""[..]),
+            (b"f", b"Zg=="),
+            (b"fo", b"Zm8="),
+            (b"foo", b"Zm9v"),
+            (b"foob", b"Zm9vYg=="),
+            (b"fooba", b"Zm9vYmE="),
+            (b"foobar", b"Zm9vYmFy"),
+            (b"\xff\xfe\x00", b"//4A"),
+        ] {
+            let mut encoder = Encoder::new();
+            let mut encoded = [0; 8];
+            let mut len = 0;
+            for byte in bytes {
+                if let Some(chars) = encoder.push(*byte) {
+                    encoded[len..len + 4].copy_from_slice(&chars);
+                    len += 4;
+                }
+            }
+            if let Some(chars) = encoder.finish() {
+                encoded[len..len + 4].copy_from_slice(&chars);
+                len += 4;
+            }
+            assert_eq!(&encoded[..len], text);
+
+            let mut decoder = Decoder::new(text);
+            for byte in bytes {
+                assert_eq!(decoder.next(), Ok(Some(*byte)));
+            }
+            assert_eq!(decoder.next(), Ok(None));
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        for text in &[&b"Zg="[..], b"Zg==Zg==", b"Z!==", b"Zm9v Zg=="] {
+            let mut decoder = Decoder::new(text);
+            let mut result = Ok(Some(0));
+            while let Ok(Some(_)) = result {
+                result = decoder.next();
+            }
+            assert_eq!(result, Err(()));
+        }
+    }
+}
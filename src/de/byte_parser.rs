@@ -0,0 +1,217 @@
+/// Status reported by `ByteParser::push_byte`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParseStatus {
+    /// The byte completed a JSON value
+    Complete,
+    /// The value is not complete yet
+    Incomplete,
+    /// The input is malformed or nests too deeply; call `reset` to start over
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    /// Waiting for the first byte of a value
+    Start,
+    /// Inside an object or array, but not inside a string
+    Container,
+    /// Inside a string
+    String,
+    /// Right after a `\` inside a string
+    Escape,
+    /// Inside a top level number, `true`, `false` or `null`
+    Scalar,
+    Error,
+}
+
+/// Maximum nesting depth tracked by `ByteParser`
+const MAX_DEPTH: u8 = 32;
+
+/// Detects where a JSON value ends while it's being received one byte at a time
+///
+/// This is meant to find message boundaries cheaply, e.g. from a UART receive interrupt, without
+/// buffering the document twice. Only the structure of the input (nesting, strings and escapes) is
+/// tracked so the received bytes still need to be deserialized with `from_slice` once the value is
+/// complete.
+///
+/// Objects, arrays and strings are complete on their closing byte. Top level numbers and literals
+/// have no closing byte; they are reported as complete on the first whitespace byte that follows
+/// them. After a value is complete the parser is ready to receive the next one.
+pub struct ByteParser {
+    /// One bit per open container, set for objects; the innermost container is the lowest bit
+    containers: u32,
+    depth: u8,
+    state: State,
+}
+
+impl ByteParser {
+    /// Creates a parser that's waiting for the start of a value
+    pub fn new() -> Self {
+        ByteParser {
+            containers: 0,
+            depth: 0,
+            state: State::Start,
+        }
+    }
+
+    /// Feeds the next byte of input
+    ///
+    /// Returns `None` for whitespace received before a value starts
+    pub fn push_byte(&mut self, byte: u8) -> Option<ParseStatus> {
+        let status = match self.state {
+            State::Start => match byte {
+                b' ' | b'\n' | b'\t' | b'\r' => return None,
+                b'{' | b'[' => self.open(byte == b'{'),
+                b'"' => {
+                    self.state = State::String;
+                    ParseStatus::Incomplete
+                }
+                b'}' | b']' | b',' | b':' => self.error(),
+                _ => {
+                    self.state = State::Scalar;
+                    ParseStatus::Incomplete
+                }
+            },
+            State::Container => match byte {
+                b'{' | b'[' => self.open(byte == b'{'),
+                b'}' | b']' => self.close(byte == b'}'),
+                b'"' => {
+                    self.state = State::String;
+                    ParseStatus::Incomplete
+                }
+                _ => ParseStatus::Incomplete,
+            },
+            State::String => match byte {
+                b'\\' => {
+                    self.state = State::Escape;
+                    ParseStatus::Incomplete
+                }
+                b'"' => {
+                    if self.depth == 0 {
+                        self.state = State::Start;
+                        ParseStatus::Complete
+                    } else {
+                        self.state = State::Container;
+                        ParseStatus::Incomplete
+                    }
+                }
+                _ => ParseStatus::Incomplete,
+            },
+            State::Escape => {
+                self.state = State::String;
+                ParseStatus::Incomplete
+            }
+            State::Scalar => match byte {
+                b' ' | b'\n' | b'\t' | b'\r' => {
+                    self.state = State::Start;
+                    ParseStatus::Complete
+                }
+                b'{' | b'[' | b'}' | b']' | b'"' | b',' | b':' => self.error(),
+                _ => ParseStatus::Incomplete,
+            },
+            State::Error => ParseStatus::Error,
+        };
+
+        Some(status)
+    }
+
+    /// Discards any partially received value
+    pub fn reset(&mut self) {
+        *self = ByteParser::new();
+    }
+
+    fn close(&mut self, object: bool) -> ParseStatus {
+        if (self.containers & 1 == 1) != object {
+            return self.error();
+        }
+
+        self.containers >>= 1;
+        self.depth -= 1;
+
+        if self.depth == 0 {
+            self.state = State::Start;
+            ParseStatus::Complete
+        } else {
+            ParseStatus::Incomplete
+        }
+    }
+
+    fn error(&mut self) -> ParseStatus {
+        self.state = State::Error;
+        ParseStatus::Error
+    }
+
+    fn open(&mut self, object: bool) -> ParseStatus {
+        if self.depth == MAX_DEPTH {
+            return self.error();
+        }
+
+        self.containers = self.containers << 1 | object as u32;
+        self.depth += 1;
+        self.state = State::Container;
+        ParseStatus::Incomplete
+    }
+}
+
+impl Default for ByteParser {
+    fn default() -> Self {
+        ByteParser::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ByteParser, ParseStatus};
+
+    /// Feeds `input` and returns the index of the byte that completed a value
+    fn complete_at(parser: &mut ByteParser, input: &[u8]) -> Option<usize> {
+        for (i, byte) in input.iter().enumerate() {
+            match parser.push_byte(*byte) {
+                Some(ParseStatus::Complete) => return Some(i),
+                Some(ParseStatus::Error) => panic!("error at byte {}", i),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    #[test]
+    fn error() {
+        let mut parser = ByteParser::new();
+        assert_eq!(parser.push_byte(b'{'), Some(ParseStatus::Incomplete));
+        assert_eq!(parser.push_byte(b']'), Some(ParseStatus::Error));
+        assert_eq!(parser.push_byte(b'}'), Some(ParseStatus::Error));
+
+        parser.reset();
+        assert_eq!(parser.push_byte(b'}'), Some(ParseStatus::Error));
+
+        parser.reset();
+        for _ in 0..32 {
+            assert_eq!(parser.push_byte(b'['), Some(ParseStatus::Incomplete));
+        }
+        assert_eq!(parser.push_byte(b'['), Some(ParseStatus::Error));
+    }
+
+    #[test]
+    fn object() {
+        let input = br#"{"a":[1,{"b":"}]"}],"c":"\"{"} {"d":null}"#;
+
+        let mut parser = ByteParser::new();
+        let end = complete_at(&mut parser, input).unwrap();
+        assert_eq!(end, input.iter().position(|b| *b == b' ').unwrap() - 1);
+
+        // leading whitespace is not part of the next value
+        assert_eq!(parser.push_byte(b' '), None);
+        assert_eq!(complete_at(&mut parser, &input[end + 2..]), Some(9));
+    }
+
+    #[test]
+    fn scalar() {
+        let mut parser = ByteParser::new();
+        assert_eq!(complete_at(&mut parser, b"\"a\\\"b\""), Some(5));
+        assert_eq!(complete_at(&mut parser, b"-12.5\n"), Some(5));
+        assert_eq!(complete_at(&mut parser, b"true"), None);
+        assert_eq!(complete_at(&mut parser, b" "), Some(0));
+    }
+}
@@ -1,20 +1,23 @@
 use serde::de;
+use serde::de::value::BorrowedStrDeserializer;
 use de::{Deserializer, Error, Result};
 
-pub(crate) struct UnitVariantAccess<'a, 'b>
+/// Variant written as a string, e.g. `"A"`; only unit variants can be written like this
+pub(crate) struct UnitVariantAccess<'a, 'b, 's>
 where
     'b: 'a,
+    's: 'a,
 {
-    de: &'a mut Deserializer<'b>,
+    de: &'a mut Deserializer<'b, 's>,
 }
 
-impl<'a, 'b> UnitVariantAccess<'a, 'b> {
-    pub(crate) fn new(de: &'a mut Deserializer<'b>) -> Self {
+impl<'a, 'b, 's> UnitVariantAccess<'a, 'b, 's> {
+    pub(crate) fn new(de: &'a mut Deserializer<'b, 's>) -> Self {
         UnitVariantAccess { de }
     }
 }
 
-impl<'a, 'de> de::EnumAccess<'de> for UnitVariantAccess<'a, 'de> {
+impl<'a, 'de, 's> de::EnumAccess<'de> for UnitVariantAccess<'a, 'de, 's> {
     type Error = Error;
     type Variant = Self;
 
@@ -27,7 +30,7 @@ impl<'a, 'de> de::EnumAccess<'de> for UnitVariantAccess<'a, 'de> {
     }
 }
 
-impl<'de, 'a> de::VariantAccess<'de> for UnitVariantAccess<'a, 'de> {
+impl<'de, 'a, 's> de::VariantAccess<'de> for UnitVariantAccess<'a, 'de, 's> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
@@ -55,3 +58,144 @@ impl<'de, 'a> de::VariantAccess<'de> for UnitVariantAccess<'a, 'de> {
         Err(Error::InvalidType)
     }
 }
+
+/// Variant written as the only key of an object, e.g. `{"B":1}`
+pub(crate) struct VariantAccess<'a, 'b, 's>
+where
+    'b: 'a,
+    's: 'a,
+{
+    de: &'a mut Deserializer<'b, 's>,
+}
+
+impl<'a, 'b, 's> VariantAccess<'a, 'b, 's> {
+    pub(crate) fn new(de: &'a mut Deserializer<'b, 's>) -> Self {
+        VariantAccess { de }
+    }
+}
+
+impl<'a, 'de, 's> de::EnumAccess<'de> for VariantAccess<'a, 'de, 's> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(&mut *self.de)?;
+        self.de.parse_object_colon()?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, 's> de::VariantAccess<'de> for VariantAccess<'a, 'de, 's> {
+    type Error = Error;
+
+    /// `{"A":null}`, only accepted with `DeserializerConfig::lenient_enum`
+    fn unit_variant(self) -> Result<()> {
+        if !self.de.config.lenient_enum {
+            return Err(Error::InvalidType);
+        }
+
+        match self.de.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+            b'n' => {
+                self.de.eat_char();
+                self.de.parse_ident(b"ull")
+            }
+            _ => Err(Error::InvalidType),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_tuple(&mut *self.de, len, visitor)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(&mut *self.de, visitor)
+    }
+}
+
+/// Variant named by the tag field of the object, e.g. `{"type":"A","x":1}`, see
+/// `DeserializerConfig::enum_tag`
+///
+/// The deserializer is still at the opening `{`; `Deserializer::find_tag` only looked ahead.
+pub(crate) struct TaggedVariantAccess<'a, 'b, 's>
+where
+    'b: 'a,
+    's: 'a,
+{
+    de: &'a mut Deserializer<'b, 's>,
+    tag: &'static str,
+    variant: &'b str,
+}
+
+impl<'a, 'b, 's> TaggedVariantAccess<'a, 'b, 's> {
+    pub(crate) fn new(
+        de: &'a mut Deserializer<'b, 's>,
+        variant: &'b str,
+        tag: &'static str,
+    ) -> Self {
+        TaggedVariantAccess { de, tag, variant }
+    }
+}
+
+impl<'a, 'de, 's> de::EnumAccess<'de> for TaggedVariantAccess<'a, 'de, 's> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(BorrowedStrDeserializer::new(self.variant))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, 's> de::VariantAccess<'de> for TaggedVariantAccess<'a, 'de, 's> {
+    type Error = Error;
+
+    /// `{"type":"C"}`; like `serde`, the other fields are ignored
+    fn unit_variant(self) -> Result<()> {
+        self.de.skip_value()
+    }
+
+    /// The object minus the tag is the content of the variant
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.de.skip_key = Some(self.tag);
+        let ret = seed.deserialize(&mut *self.de);
+        self.de.skip_key = None;
+        ret
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::InvalidType)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.de.skip_key = Some(self.tag);
+        de::Deserializer::deserialize_map(&mut *self.de, visitor)
+    }
+}
@@ -2,50 +2,67 @@ use serde::de::{self, Visitor};
 
 use de::{Deserializer, Error};
 
-pub struct MapAccess<'a, 'b>
+pub struct MapAccess<'a, 'b, 's>
 where
     'b: 'a,
+    's: 'a,
 {
-    de: &'a mut Deserializer<'b>,
+    de: &'a mut Deserializer<'b, 's>,
     first: bool,
+    /// Entry that is not handed to the visitor, see `Deserializer::skip_key`
+    skip: Option<&'static str>,
 }
 
-impl<'a, 'b> MapAccess<'a, 'b> {
-    pub(crate) fn new(de: &'a mut Deserializer<'b>) -> Self {
-        MapAccess { de, first: true }
+impl<'a, 'b, 's> MapAccess<'a, 'b, 's> {
+    pub(crate) fn new(de: &'a mut Deserializer<'b, 's>, skip: Option<&'static str>) -> Self {
+        MapAccess {
+            de,
+            first: true,
+            skip,
+        }
     }
 }
 
-impl<'a, 'de> de::MapAccess<'de> for MapAccess<'a, 'de> {
+impl<'a, 'de, 's> de::MapAccess<'de> for MapAccess<'a, 'de, 's> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
     where
         K: de::DeserializeSeed<'de>,
     {
-        let peek = match self.de
-            .parse_whitespace()
-            .ok_or(Error::EofWhileParsingObject)?
-        {
-            b'}' => return Ok(None),
-            b',' if !self.first => {
-                self.de.eat_char();
-                self.de.parse_whitespace()
-            }
-            b => {
-                if self.first {
-                    self.first = false;
-                    Some(b)
-                } else {
-                    return Err(Error::ExpectedObjectCommaOrEnd);
+        loop {
+            let peek = match self.de
+                .parse_whitespace()
+                .ok_or(Error::EofWhileParsingObject)?
+            {
+                b'}' => return Ok(None),
+                b',' if !self.first => {
+                    self.de.eat_char();
+                    self.de.parse_whitespace()
                 }
-            }
-        };
+                b => {
+                    if self.first {
+                        self.first = false;
+                        Some(b)
+                    } else {
+                        return Err(Error::ExpectedObjectCommaOrEnd);
+                    }
+                }
+            };
 
-        match peek.ok_or(Error::EofWhileParsingValue)? {
-            b'"' => seed.deserialize(MapKey { de: &mut *self.de }).map(Some),
-            b'}' => Err(Error::TrailingComma),
-            _ => Err(Error::KeyMustBeAString),
+            match peek.ok_or(Error::EofWhileParsingValue)? {
+                b'"' => {
+                    if let Some(key) = self.skip {
+                        if self.de.skip_entry(key)? {
+                            continue;
+                        }
+                    }
+
+                    return seed.deserialize(MapKey { de: &mut *self.de }).map(Some);
+                }
+                b'}' => return Err(Error::TrailingComma),
+                _ => return Err(Error::KeyMustBeAString),
+            }
         }
     }
 
@@ -59,105 +76,117 @@ impl<'a, 'de> de::MapAccess<'de> for MapAccess<'a, 'de> {
     }
 }
 
-struct MapKey<'a, 'b>
+struct MapKey<'a, 'b, 's>
 where
     'b: 'a,
+    's: 'a,
 {
-    de: &'a mut Deserializer<'b>,
+    de: &'a mut Deserializer<'b, 's>,
 }
 
-impl<'de, 'a> de::Deserializer<'de> for MapKey<'a, 'de> {
+/// Deserializes a number from a key, which is always quoted, e.g. the `1` of `{"1":true}`
+macro_rules! deserialize_quoted {
+    ($self:ident, $visitor:ident, $deserialize:ident) => {{
+        let parse_stringified_numbers = $self.de.config.parse_stringified_numbers;
+        $self.de.config.parse_stringified_numbers = true;
+        let ret = de::Deserializer::$deserialize(&mut *$self.de, $visitor);
+        $self.de.config.parse_stringified_numbers = parse_stringified_numbers;
+        ret
+    }};
+}
+
+impl<'de, 'a, 's> de::Deserializer<'de> for MapKey<'a, 'de, 's> {
     type Error = Error;
 
-    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Error>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        self.deserialize_str(visitor)
     }
 
     fn deserialize_bool<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::InvalidType)
     }
 
-    fn deserialize_i8<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_quoted!(self, visitor, deserialize_i8)
     }
 
-    fn deserialize_i16<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_quoted!(self, visitor, deserialize_i16)
     }
 
-    fn deserialize_i32<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_quoted!(self, visitor, deserialize_i32)
     }
 
-    fn deserialize_i64<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_quoted!(self, visitor, deserialize_i64)
     }
 
-    fn deserialize_u8<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_quoted!(self, visitor, deserialize_u8)
     }
 
-    fn deserialize_u16<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_quoted!(self, visitor, deserialize_u16)
     }
 
-    fn deserialize_u32<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_quoted!(self, visitor, deserialize_u32)
     }
 
-    fn deserialize_u64<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_quoted!(self, visitor, deserialize_u64)
     }
 
-    fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_quoted!(self, visitor, deserialize_f32)
     }
 
-    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_quoted!(self, visitor, deserialize_f64)
     }
 
-    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        self.deserialize_str(visitor)
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -167,39 +196,39 @@ impl<'de, 'a> de::Deserializer<'de> for MapKey<'a, 'de> {
         self.de.deserialize_str(visitor)
     }
 
-    fn deserialize_string<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        self.de.deserialize_str(visitor)
     }
 
     fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::InvalidType)
     }
 
     fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::InvalidType)
     }
 
     fn deserialize_option<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::InvalidType)
     }
 
     fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::InvalidType)
     }
 
     fn deserialize_unit_struct<V>(
@@ -210,32 +239,32 @@ impl<'de, 'a> de::Deserializer<'de> for MapKey<'a, 'de> {
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::InvalidType)
     }
 
     fn deserialize_newtype_struct<V>(
         self,
         _name: &'static str,
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        visitor.visit_newtype_struct(self)
     }
 
     fn deserialize_seq<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::InvalidType)
     }
 
     fn deserialize_tuple<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::InvalidType)
     }
 
     fn deserialize_tuple_struct<V>(
@@ -247,14 +276,14 @@ impl<'de, 'a> de::Deserializer<'de> for MapKey<'a, 'de> {
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::InvalidType)
     }
 
     fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::InvalidType)
     }
 
     fn deserialize_struct<V>(
@@ -266,19 +295,19 @@ impl<'de, 'a> de::Deserializer<'de> for MapKey<'a, 'de> {
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::InvalidType)
     }
 
     fn deserialize_enum<V>(
         self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
-        _visitor: V,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        de::Deserializer::deserialize_enum(self.de, name, variants, visitor)
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -288,10 +317,10 @@ impl<'de, 'a> de::Deserializer<'de> for MapKey<'a, 'de> {
         self.deserialize_str(visitor)
     }
 
-    fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        de::Deserializer::deserialize_ignored_any(self.de, visitor)
     }
 }
@@ -1,13 +1,17 @@
 //! Deserialize JSON data to a Rust data structure
 
-use core::{fmt, str};
+use core::{cmp, fmt, str};
 
+use heapless::{String, Vec};
 use serde::de::{self, Visitor};
 
-use self::enum_::UnitVariantAccess;
+use self::enum_::{TaggedVariantAccess, UnitVariantAccess, VariantAccess};
 use self::map::MapAccess;
-use self::seq::SeqAccess;
+use self::seq::{HexAccess, PackedAccess, SeqAccess};
 
+pub use self::byte_parser::{ByteParser, ParseStatus};
+
+mod byte_parser;
 mod enum_;
 mod map;
 mod seq;
@@ -17,7 +21,20 @@ pub type Result<T> = ::core::result::Result<T, Error>;
 
 /// This type represents all possible errors that can occur when deserializing JSON data
 #[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
+    /// Element of a byte array is greater than 255, see `DeserializerConfig::bytes_base64`.
+    ByteOutOfRange,
+
+    /// The CRC field is missing or doesn't match the message, see `from_slice_with_crc`.
+    ChecksumMismatch,
+
+    /// Error reported by a `Deserialize` implementation with a custom message, which is dropped.
+    Custom,
+
+    /// Object has the same field twice.
+    DuplicateField,
+
     /// EOF while parsing a list.
     EofWhileParsingList,
 
@@ -30,6 +47,12 @@ pub enum Error {
     /// EOF while parsing a JSON value.
     EofWhileParsingValue,
 
+    /// String contains escape sequences and can't be borrowed from the input.
+    EscapeInBorrowedStr,
+
+    /// Unescaped string doesn't fit in the scratch buffer, see `from_slice_escaped`.
+    EscapedStringTooLong,
+
     /// Expected this character to be a `':'`.
     ExpectedColon,
 
@@ -45,6 +68,18 @@ pub enum Error {
     /// Expected this character to start a JSON value.
     ExpectedSomeValue,
 
+    /// Invalid base64 in a packed array, see `DeserializerConfig::pack_arrays`.
+    InvalidBase64,
+
+    /// Invalid ISO-8601 duration, see `helpers::Iso8601Duration`.
+    InvalidDuration,
+
+    /// Invalid escape sequence in a string, e.g. a truncated `\u` escape or a lone surrogate.
+    InvalidEscape,
+
+    /// Invalid hex in a byte string, see `DeserializerConfig::bytes_hex`.
+    InvalidHex,
+
     /// Invalid number.
     InvalidNumber,
 
@@ -57,37 +92,219 @@ pub enum Error {
     /// Object key is not a string.
     KeyMustBeAString,
 
+    /// Array is shorter than the tuple and a missing element is not an `Option`.
+    MissingElement,
+
+    /// Object lacks a field of the struct that is not an `Option` and has no default.
+    MissingField,
+
+    /// Array has more elements, or string more bytes, than the output can hold.
+    SeqTooLong,
+
+    /// Object has more unknown fields than the output vector can hold.
+    TooManyUnknownFields,
+
     /// JSON has non-whitespace trailing characters after the value.
     TrailingCharacters,
 
     /// JSON has a comma after the last value in an array or map.
     TrailingComma,
 
-    #[doc(hidden)]
-    __Extensible,
+    /// Object has a field that is not one of the fields of the struct, see
+    /// `#[serde(deny_unknown_fields)]`.
+    UnknownField,
+
+    /// Enum variant is not one of the variants of the enum.
+    UnknownVariant,
 }
 
-#[cfg(feature = "std")]
-impl ::std::error::Error for Error {
-    fn description(&self) -> &str {
-        ""
-    }
+impl ::core::error::Error for Error {}
+
+/// Options that tweak how JSON data is deserialized
+///
+/// The default configuration matches the default `ser::SerializerConfig`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DeserializerConfig {
+    /// Accept the JavaScript literals `NaN`, `Infinity` and `-Infinity` for floats
+    ///
+    /// This is the counterpart of `ser::NonFinitePolicy::AsLiteral`. By default these literals are
+    /// rejected with `Error::InvalidNumber`.
+    pub allow_non_finite: bool,
+
+    /// Accept underscores between the digits of numbers, e.g. `1_000_000`
+    ///
+    /// Each underscore must sit between two digits; leading, trailing and doubled underscores are
+    /// still rejected. By default any underscore is rejected.
+    pub allow_underscores: bool,
+
+    /// Tell `Deserialize` implementations that the input is not human readable
+    ///
+    /// This is the counterpart of `ser::SerializerConfig::binary`; e.g. `Ipv4Addr` is then
+    /// expected in its compact form, `[127,0,0,1]`.
+    pub binary: bool,
+
+    /// Accept byte strings, e.g. `serde_bytes::ByteBuf`, written as a base64 string
+    ///
+    /// This is the counterpart of `ser::BytesFormat::Base64`. By default a string is taken as its
+    /// raw UTF-8 bytes, like `serde_json`, which lets a `&[u8]` borrow it from the input. Byte
+    /// strings can always be written as an array of numbers instead, e.g. `[1,2,255]`; elements
+    /// greater than 255 are rejected with `Error::ByteOutOfRange`.
+    pub bytes_base64: bool,
+
+    /// Accept byte strings written as a hex string, e.g. `"0102ff"`
+    ///
+    /// This is the counterpart of `ser::BytesFormat::Hex`. Both upper and lower case digits are
+    /// accepted; an odd number of digits is rejected with `Error::InvalidHex`. If `bytes_base64`
+    /// is set as well, strings are decoded as base64.
+    pub bytes_hex: bool,
+
+    /// Read enums written as objects as internally tagged by this key, e.g. `{"type":"A","x":1}`
+    /// for the variant `A { x: u8 }` with a tag of `"type"`
+    ///
+    /// This is the no_std counterpart of `#[serde(tag = "type")]`, whose derived `Deserialize`
+    /// buffers the fields in front of the tag and thus needs `std`. Derive `Deserialize` without
+    /// the attribute and set this option instead: the object is scanned for the tag first, which
+    /// can be anywhere in the object, and then read again as the variant, minus the tag. Struct
+    /// variants, and newtype variants wrapping a struct or a map, are supported; unit variants
+    /// ignore the rest of the object. Fails with `Error::MissingField` if there's no tag. Applies
+    /// to all the enums in the input; unit variants can still be written as plain strings.
+    pub enum_tag: Option<&'static str>,
+
+    /// Also accept unit variants of enums written as an object with a single `null` field, e.g.
+    /// `{"A":null}` in addition to `"A"`
+    pub lenient_enum: bool,
+
+    /// Accept arrays of `u8` or `u16` packed into a base64 string, e.g. `"u8:AQID"` for `[1,2,3]`
+    ///
+    /// This is the counterpart of `ser::SerializerConfig::pack_arrays`. Fails with
+    /// `Error::InvalidBase64` if the string is not valid base64, or doesn't hold a whole number of
+    /// `u16`s, and with `Error::SeqTooLong` if it holds more elements than the output array. Arrays
+    /// written as usual are still accepted.
+    pub pack_arrays: bool,
+
+    /// Accept numbers wrapped in a string, e.g. `{"count":"42"}`, for numeric fields
+    ///
+    /// The whole content of the string must be a valid number of the requested type. By default a
+    /// string where a number is expected is rejected with `Error::InvalidType`.
+    pub parse_stringified_numbers: bool,
 }
 
-pub(crate) struct Deserializer<'b> {
+pub(crate) struct Deserializer<'b, 's> {
     slice: &'b [u8],
     index: usize,
+    config: DeserializerConfig,
+    /// Fields of the first struct that was deserialized, i.e. of the top level struct if any
+    fields: Option<&'static [&'static str]>,
+    /// Buffer that strings with escape sequences are unescaped into, see `from_slice_escaped`
+    scratch: Option<&'s mut [u8]>,
+    /// Key that the next object skips: the tag of an internally tagged enum, see
+    /// `DeserializerConfig::enum_tag`
+    skip_key: Option<&'static str>,
 }
 
-impl<'a> Deserializer<'a> {
-    fn new(slice: &'a [u8]) -> Deserializer {
-        Deserializer { slice, index: 0 }
+impl<'a, 's> Deserializer<'a, 's> {
+    fn new(slice: &'a [u8], config: DeserializerConfig) -> Self {
+        Deserializer {
+            slice,
+            index: 0,
+            config,
+            fields: None,
+            scratch: None,
+            skip_key: None,
+        }
+    }
+
+    /// Finds the string value of the `tag` key of the object that starts at the current position,
+    /// without consuming the object
+    fn find_tag(&mut self, tag: &str) -> Result<&'a str> {
+        let start = self.index;
+        self.eat_char();
+
+        let mut first = true;
+        loop {
+            match self.parse_whitespace().ok_or(Error::EofWhileParsingObject)? {
+                b'}' => return Err(Error::MissingField),
+                b',' if !first => {
+                    self.eat_char();
+                    self.parse_whitespace();
+                }
+                _ if first => first = false,
+                _ => return Err(Error::ExpectedObjectCommaOrEnd),
+            }
+
+            match self.peek().ok_or(Error::EofWhileParsingObject)? {
+                b'"' => self.eat_char(),
+                b'}' => return Err(Error::TrailingComma),
+                _ => return Err(Error::KeyMustBeAString),
+            }
+            let key = self.parse_str()?;
+            self.parse_object_colon()?;
+
+            if key == tag {
+                match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+                    b'"' => self.eat_char(),
+                    _ => return Err(Error::InvalidType),
+                }
+                let variant = self.parse_str()?;
+
+                self.index = start;
+                return Ok(variant);
+            }
+
+            self.skip_value()?;
+        }
+    }
+
+    /// Skips the object entry at the current position, a `"` of a key, if its key is `key`
+    fn skip_entry(&mut self, key: &str) -> Result<bool> {
+        let start = self.index;
+        self.eat_char();
+
+        match self.parse_str() {
+            Ok(k) if k == key => {
+                self.parse_object_colon()?;
+                self.skip_value()?;
+                Ok(true)
+            }
+            _ => {
+                self.index = start;
+                Ok(false)
+            }
+        }
     }
 
     fn eat_char(&mut self) {
         self.index += 1;
     }
 
+    /// Consumes a run of digits and returns how many were consumed
+    fn eat_digits(&mut self) -> usize {
+        let start = self.index;
+        loop {
+            match self.peek() {
+                Some(b'0'..=b'9') => self.eat_char(),
+                // see `DeserializerConfig::allow_underscores`
+                Some(b'_') if self.config.allow_underscores && self.index != start => {
+                    match self.slice.get(self.index + 1) {
+                        Some(&(b'0'..=b'9')) => self.eat_char(),
+                        _ => break,
+                    }
+                }
+                _ => break,
+            }
+        }
+        self.index - start
+    }
+
+    /// Consumes an underscore that follows a digit, checking that a digit comes next
+    fn eat_underscore(&mut self) -> Result<()> {
+        self.eat_char();
+        match self.peek() {
+            Some(b'0'..=b'9') => Ok(()),
+            _ => Err(Error::InvalidNumber),
+        }
+    }
+
     fn end(&mut self) -> Result<()> {
         match self.parse_whitespace() {
             Some(_) => Err(Error::TrailingCharacters),
@@ -143,6 +360,93 @@ impl<'a> Deserializer<'a> {
         Ok(())
     }
 
+    /// Consumes one of the JavaScript literals `NaN`, `Infinity` or `-Infinity`, if present
+    fn parse_non_finite(&mut self) -> Option<f64> {
+        let rest = &self.slice[self.index..];
+
+        let (literal, v): (&[u8], _) = if rest.starts_with(b"NaN") {
+            (b"NaN", f64::NAN)
+        } else if rest.starts_with(b"Infinity") {
+            (b"Infinity", f64::INFINITY)
+        } else if rest.starts_with(b"-Infinity") {
+            (b"-Infinity", f64::NEG_INFINITY)
+        } else {
+            return None;
+        };
+
+        self.index += literal.len();
+        Some(v)
+    }
+
+    /// Checks the closing quote of a number that `parse_number_start` found to be quoted
+    fn parse_number_end(&mut self, quoted: bool) -> Result<()> {
+        if !quoted {
+            return Ok(());
+        }
+
+        match self.peek().ok_or(Error::EofWhileParsingString)? {
+            b'"' => {
+                self.eat_char();
+                Ok(())
+            }
+            _ => Err(Error::InvalidNumber),
+        }
+    }
+
+    /// Consumes the whitespace in front of a number, plus its opening quote if
+    /// `parse_stringified_numbers` is enabled and the number is quoted
+    ///
+    /// Returns whether the number is quoted
+    fn parse_number_start(&mut self) -> Result<bool> {
+        match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+            b'"' if self.config.parse_stringified_numbers => {
+                self.eat_char();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Consumes a number, checking that it follows the JSON grammar, and returns its text
+    fn parse_number(&mut self) -> Result<&'a str> {
+        let start = self.index;
+
+        if self.peek() == Some(b'-') {
+            self.eat_char();
+        }
+
+        match self.peek() {
+            Some(b'0') => self.eat_char(),
+            Some(b'1'..=b'9') => {
+                self.eat_digits();
+            }
+            _ => return Err(Error::InvalidNumber),
+        }
+
+        if self.peek() == Some(b'.') {
+            self.eat_char();
+
+            if self.eat_digits() == 0 {
+                return Err(Error::InvalidNumber);
+            }
+        }
+
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            self.eat_char();
+
+            if let Some(b'+') | Some(b'-') = self.peek() {
+                self.eat_char();
+            }
+
+            if self.eat_digits() == 0 {
+                return Err(Error::InvalidNumber);
+            }
+        }
+
+        // NOTE(unsafe) the grammar above only accepts ASCII characters
+        Ok(unsafe { str::from_utf8_unchecked(&self.slice[start..self.index]) })
+    }
+
     fn parse_object_colon(&mut self) -> Result<()> {
         match self.parse_whitespace().ok_or(Error::EofWhileParsingObject)? {
             b':' => {
@@ -153,22 +457,118 @@ impl<'a> Deserializer<'a> {
         }
     }
 
+    /// Parses a packed array, see `DeserializerConfig::pack_arrays`
+    fn parse_packed(&mut self, tuple: bool) -> Result<PackedAccess<'a>> {
+        self.eat_char();
+        let s = self.parse_str()?;
+
+        if let Some(data) = s.strip_prefix("u8:") {
+            Ok(PackedAccess::new(data.as_bytes(), false, tuple))
+        } else if let Some(data) = s.strip_prefix("u16:") {
+            Ok(PackedAccess::new(data.as_bytes(), true, tuple))
+        } else {
+            Err(Error::InvalidType)
+        }
+    }
+
     fn parse_str(&mut self) -> Result<&'a str> {
         let start = self.index;
+        let mut ascii = true;
         loop {
             match self.peek() {
                 Some(b'"') => {
                     let end = self.index;
                     self.eat_char();
-                    return str::from_utf8(&self.slice[start..end])
-                        .map_err(|_| Error::InvalidUnicodeCodePoint);
+
+                    let bytes = &self.slice[start..end];
+                    return if ascii {
+                        // NOTE(parse_str) ASCII is valid UTF-8; most strings are plain ASCII so
+                        // this skips a second pass over them
+                        Ok(unsafe { str::from_utf8_unchecked(bytes) })
+                    } else {
+                        str::from_utf8(bytes).map_err(|_| Error::InvalidUnicodeCodePoint)
+                    };
+                }
+                // NOTE(parse_str) an escaped string would need to be copied into a new buffer to be
+                // unescaped; returning the raw slice would silently hand out the wrong contents
+                Some(b'\\') => return Err(Error::EscapeInBorrowedStr),
+                Some(c) => {
+                    ascii &= c < 0x80;
+                    self.eat_char();
                 }
-                Some(_) => self.eat_char(),
                 None => return Err(Error::EofWhileParsingString),
             }
         }
     }
 
+    /// Parses the rest of a string that contains escape sequences, unescaping it into `scratch`
+    fn parse_escaped_str(&mut self) -> Result<&str> {
+        let slice = self.slice;
+        let mut i = self.index;
+        let scratch = self.scratch.as_mut().ok_or(Error::EscapeInBorrowedStr)?;
+        let mut len = 0;
+
+        loop {
+            let byte = *slice.get(i).ok_or(Error::EofWhileParsingString)?;
+            i += 1;
+
+            let mut utf8 = [0; 4];
+            let unescaped: &[u8] = match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *slice.get(i).ok_or(Error::EofWhileParsingString)?;
+                    i += 1;
+
+                    match escape {
+                        b'"' => b"\"",
+                        b'\\' => b"\\",
+                        b'/' => b"/",
+                        b'b' => b"\x08",
+                        b'f' => b"\x0c",
+                        b'n' => b"\n",
+                        b'r' => b"\r",
+                        b't' => b"\t",
+                        b'u' => {
+                            let c = match parse_hex4(slice, &mut i)? {
+                                // astral code points are escaped as a UTF-16 surrogate pair
+                                high @ 0xd800..=0xdbff => {
+                                    if slice.get(i..i + 2) != Some(&b"\\u"[..]) {
+                                        return Err(Error::InvalidEscape);
+                                    }
+                                    i += 2;
+
+                                    match parse_hex4(slice, &mut i)? {
+                                        low @ 0xdc00..=0xdfff => {
+                                            0x10000 + ((high - 0xd800) << 10 | (low - 0xdc00))
+                                        }
+                                        _ => return Err(Error::InvalidEscape),
+                                    }
+                                }
+                                0xdc00..=0xdfff => return Err(Error::InvalidEscape),
+                                c => c,
+                            };
+
+                            let c = char::from_u32(c).ok_or(Error::InvalidEscape)?;
+                            c.encode_utf8(&mut utf8).as_bytes()
+                        }
+                        _ => return Err(Error::InvalidEscape),
+                    }
+                }
+                _ => &slice[i - 1..i],
+            };
+
+            let end = len + unescaped.len();
+            if end > scratch.len() {
+                return Err(Error::EscapedStringTooLong);
+            }
+            scratch[len..end].copy_from_slice(unescaped);
+            len = end;
+        }
+
+        self.index = i;
+        str::from_utf8(&scratch[..len]).map_err(|_| Error::InvalidUnicodeCodePoint)
+    }
+
     /// Consumes all the whitespace characters and returns a peek into the next character
     fn parse_whitespace(&mut self) -> Option<u8> {
         loop {
@@ -186,6 +586,114 @@ impl<'a> Deserializer<'a> {
     fn peek(&mut self) -> Option<u8> {
         self.slice.get(self.index).cloned()
     }
+
+    /// Consumes any JSON value, checking its syntax but not decoding it
+    fn skip_value(&mut self) -> Result<()> {
+        match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+            b'n' => {
+                self.eat_char();
+                self.parse_ident(b"ull")
+            }
+            b't' => {
+                self.eat_char();
+                self.parse_ident(b"rue")
+            }
+            b'f' => {
+                self.eat_char();
+                self.parse_ident(b"alse")
+            }
+            b'-' | b'0'..=b'9' => self.parse_number().map(|_| ()),
+            b'"' => {
+                self.eat_char();
+                loop {
+                    match self.next_char().ok_or(Error::EofWhileParsingString)? {
+                        b'"' => return Ok(()),
+                        b'\\' => {
+                            self.next_char().ok_or(Error::EofWhileParsingString)?;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            b'[' => {
+                self.eat_char();
+                if self.parse_whitespace() == Some(b']') {
+                    self.eat_char();
+                    return Ok(());
+                }
+
+                loop {
+                    self.skip_value()?;
+
+                    match self.parse_whitespace().ok_or(Error::EofWhileParsingList)? {
+                        b',' => self.eat_char(),
+                        b']' => {
+                            self.eat_char();
+                            return Ok(());
+                        }
+                        _ => return Err(Error::ExpectedListCommaOrEnd),
+                    }
+                }
+            }
+            b'{' => {
+                self.eat_char();
+                if self.parse_whitespace() == Some(b'}') {
+                    self.eat_char();
+                    return Ok(());
+                }
+
+                loop {
+                    match self.parse_whitespace().ok_or(Error::EofWhileParsingObject)? {
+                        b'"' => self.skip_value()?,
+                        _ => return Err(Error::KeyMustBeAString),
+                    }
+                    self.parse_object_colon()?;
+                    self.skip_value()?;
+
+                    match self.parse_whitespace().ok_or(Error::EofWhileParsingObject)? {
+                        b',' => self.eat_char(),
+                        b'}' => {
+                            self.eat_char();
+                            return Ok(());
+                        }
+                        _ => return Err(Error::ExpectedObjectCommaOrEnd),
+                    }
+                }
+            }
+            _ => Err(Error::ExpectedSomeValue),
+        }
+    }
+}
+
+/// Parses the 4 hex digits of a `\u` escape at `slice[*i..]`, advancing `i` past them
+fn parse_hex4(slice: &[u8], i: &mut usize) -> Result<u32> {
+    let digits = slice.get(*i..*i + 4).ok_or(Error::InvalidEscape)?;
+
+    let mut n = 0;
+    for digit in digits {
+        n = n << 4 | match *digit {
+            b'0'..=b'9' => digit - b'0',
+            b'a'..=b'f' => digit - b'a' + 10,
+            b'A'..=b'F' => digit - b'A' + 10,
+            _ => return Err(Error::InvalidEscape),
+        } as u32;
+    }
+
+    *i += 4;
+    Ok(n)
+}
+
+/// Removes the underscores accepted by `DeserializerConfig::allow_underscores` so that `number`
+/// can be handed to `str::parse`
+fn without_underscores<'b>(number: &'b str, digits: &'b mut String<64>) -> Result<&'b str> {
+    if !number.contains('_') {
+        return Ok(number);
+    }
+
+    for c in number.chars().filter(|c| *c != '_') {
+        digits.push(c).map_err(|_| Error::InvalidNumber)?;
+    }
+    Ok(&**digits)
 }
 
 // NOTE(deserialize_*signed) we avoid parsing into u64 and then casting to a smaller integer, which
@@ -193,21 +701,22 @@ impl<'a> Deserializer<'a> {
 // Flash, when targeting non 64-bit architectures
 macro_rules! deserialize_unsigned {
     ($self:ident, $visitor:ident, $uxx:ident, $visit_uxx:ident) => {{
-        let peek = $self.parse_whitespace().ok_or(Error::EofWhileParsingValue)?;
+        let quoted = $self.parse_number_start()?;
+        let peek = $self.peek().ok_or(Error::EofWhileParsingValue)?;
 
-        match peek {
-            b'-' => Err(Error::InvalidNumber),
+        let number = match peek {
+            b'-' => return Err(Error::InvalidNumber),
             b'0' => {
                 $self.eat_char();
-                $visitor.$visit_uxx(0)
+                0
             }
-            b'1'...b'9' => {
+            b'1'..=b'9' => {
                 $self.eat_char();
 
                 let mut number = (peek - b'0') as $uxx;
                 loop {
                     match $self.peek() {
-                        Some(c @ b'0'...b'9') => {
+                        Some(c @ b'0'..=b'9') => {
                             $self.eat_char();
                             number = number
                                 .checked_mul(10)
@@ -215,18 +724,26 @@ macro_rules! deserialize_unsigned {
                                 .checked_add((c - b'0') as $uxx)
                                 .ok_or(Error::InvalidNumber)?;
                         }
-                        _ => return $visitor.$visit_uxx(number),
+                        Some(b'_') if $self.config.allow_underscores => {
+                            $self.eat_underscore()?;
+                        }
+                        _ => break number,
                     }
                 }
             }
-            _ => Err(Error::InvalidType),
-        }
+            _ if quoted => return Err(Error::InvalidNumber),
+            _ => return Err(Error::InvalidType),
+        };
+
+        $self.parse_number_end(quoted)?;
+        $visitor.$visit_uxx(number)
     }};
 }
 
 macro_rules! deserialize_signed {
     ($self:ident, $visitor:ident, $ixx:ident, $visit_ixx:ident) => {{
-        let signed = match $self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+        let quoted = $self.parse_number_start()?;
+        let signed = match $self.peek().ok_or(Error::EofWhileParsingValue)? {
             b'-' => {
                 $self.eat_char();
                 true
@@ -234,18 +751,18 @@ macro_rules! deserialize_signed {
             _ => false,
         };
 
-        match $self.peek().ok_or(Error::EofWhileParsingValue)? {
+        let number = match $self.peek().ok_or(Error::EofWhileParsingValue)? {
             b'0' => {
                 $self.eat_char();
-                $visitor.$visit_ixx(0)
+                0
             }
-            c @ b'1'...b'9' => {
+            c @ b'1'..=b'9' => {
                 $self.eat_char();
 
                 let mut number = (c - b'0') as $ixx * if signed { -1 } else { 1 };
                 loop {
                     match $self.peek() {
-                        Some(c @ b'0'...b'9') => {
+                        Some(c @ b'0'..=b'9') => {
                             $self.eat_char();
                             number = number
                                 .checked_mul(10)
@@ -253,23 +770,89 @@ macro_rules! deserialize_signed {
                                 .checked_add((c - b'0') as $ixx * if signed { -1 } else { 1 })
                                 .ok_or(Error::InvalidNumber)?;
                         }
-                        _ => return $visitor.$visit_ixx(number),
+                        Some(b'_') if $self.config.allow_underscores => {
+                            $self.eat_underscore()?;
+                        }
+                        _ => break number,
                     }
                 }
             }
+            _ if quoted => return Err(Error::InvalidNumber),
+            _ => return Err(Error::InvalidType),
+        };
+
+        $self.parse_number_end(quoted)?;
+        $visitor.$visit_ixx(number)
+    }};
+}
+
+// NOTE(deserialize_float) `core` float parsing is correctly rounded so values produced by the
+// serializer (shortest round trip representation) are recovered bit for bit
+macro_rules! deserialize_float {
+    ($self:ident, $visitor:ident, $fxx:ident, $visit_fxx:ident) => {{
+        let quoted = $self.parse_number_start()?;
+
+        if $self.config.allow_non_finite && !quoted {
+            if let Some(v) = $self.parse_non_finite() {
+                return $visitor.$visit_fxx(v as $fxx);
+            }
+        }
+
+        match $self.peek().ok_or(Error::EofWhileParsingValue)? {
+            b'-' | b'0'..=b'9' => {}
+            b'N' | b'I' => return Err(Error::InvalidNumber),
+            _ if quoted => return Err(Error::InvalidNumber),
             _ => return Err(Error::InvalidType),
         }
+
+        let mut digits = String::new();
+        let number = without_underscores($self.parse_number()?, &mut digits)?;
+
+        let number = match number.parse::<$fxx>() {
+            // out of range literals like `1e999` are parsed as infinity
+            Ok(v) if v.is_finite() => v,
+            _ => return Err(Error::InvalidNumber),
+        };
+
+        $self.parse_number_end(quoted)?;
+        $visitor.$visit_fxx(number)
     }};
 }
 
-impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'a, 'de, 's> de::Deserializer<'de> for &'a mut Deserializer<'de, 's> {
     type Error = Error;
 
-    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+            b'n' => {
+                self.eat_char();
+                self.parse_ident(b"ull")?;
+                visitor.visit_unit()
+            }
+            b't' | b'f' => self.deserialize_bool(visitor),
+            b'-' | b'0'..=b'9' => {
+                let mut digits = String::new();
+                let number = without_underscores(self.parse_number()?, &mut digits)?;
+
+                if number.bytes().any(|b| b == b'.' || b == b'e' || b == b'E') {
+                    match number.parse::<f64>() {
+                        Ok(v) if v.is_finite() => visitor.visit_f64(v),
+                        _ => Err(Error::InvalidNumber),
+                    }
+                } else if number.starts_with('-') {
+                    visitor.visit_i64(number.parse().map_err(|_| Error::InvalidNumber)?)
+                } else {
+                    visitor.visit_u64(number.parse().map_err(|_| Error::InvalidNumber)?)
+                }
+            }
+            b'"' => self.deserialize_str(visitor),
+            b'[' => self.deserialize_seq(visitor),
+            b'{' => self.deserialize_map(visitor),
+            _ => Err(Error::ExpectedSomeValue),
+        }
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
@@ -349,25 +932,25 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         deserialize_unsigned!(self, visitor, u64, visit_u64)
     }
 
-    fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_float!(self, visitor, f32, visit_f32)
     }
 
-    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        deserialize_float!(self, visitor, f64, visit_f64)
     }
 
-    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        self.deserialize_str(visitor)
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
@@ -379,31 +962,78 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         match peek {
             b'"' => {
                 self.eat_char();
-                visitor.visit_borrowed_str(self.parse_str()?)
+
+                let start = self.index;
+                match self.parse_str() {
+                    Err(Error::EscapeInBorrowedStr) if self.scratch.is_some() => {
+                        self.index = start;
+                        let s = self.parse_escaped_str()?;
+
+                        // a borrowed `&str` can't be deserialized from the unescaped copy
+                        visitor.visit_str(s).map_err(|e| match e {
+                            Error::InvalidType => Error::EscapeInBorrowedStr,
+                            e => e,
+                        })
+                    }
+                    s => visitor.visit_borrowed_str(s?),
+                }
             }
             _ => Err(Error::InvalidType),
         }
     }
 
-    fn deserialize_string<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        self.deserialize_str(visitor)
     }
 
-    fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value>
+    /// Byte strings written as an array of numbers, or as a base64 or hex string (see
+    /// `DeserializerConfig::bytes_base64` and `DeserializerConfig::bytes_hex`), are handed to the
+    /// visitor as a sequence of `u8`s; a `&[u8]` can only be borrowed from a plain string
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+            b'[' => {
+                self.eat_char();
+                let ret = visitor.visit_seq(SeqAccess::bytes(self))?;
+
+                self.end_seq()?;
+
+                Ok(ret)
+            }
+            b'"' if self.config.bytes_base64 => {
+                self.eat_char();
+                let mut bytes = PackedAccess::new(self.parse_str()?.as_bytes(), false, false);
+                let ret = visitor.visit_seq(&mut bytes)?;
+                bytes.end()?;
+
+                Ok(ret)
+            }
+            b'"' if self.config.bytes_hex => {
+                self.eat_char();
+                let mut bytes = HexAccess::new(self.parse_str()?.as_bytes());
+                let ret = visitor.visit_seq(&mut bytes)?;
+                bytes.end()?;
+
+                Ok(ret)
+            }
+            b'"' => {
+                self.eat_char();
+                visitor.visit_borrowed_bytes(self.parse_str()?.as_bytes())
+            }
+            _ => Err(Error::InvalidType),
+        }
     }
 
-    fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        self.deserialize_bytes(visitor)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
@@ -420,32 +1050,41 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         }
     }
 
-    fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+            b'n' => {
+                self.eat_char();
+                self.parse_ident(b"ull")?;
+                visitor.visit_unit()
+            }
+            _ => Err(Error::InvalidType),
+        }
     }
 
-    fn deserialize_unit_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
+    /// Unit structs are written as `null`, like `()`
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        self.deserialize_unit(visitor)
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
+    /// Newtype structs are transparent, e.g. `12.5` for `Celsius(12.5)`
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        visitor.visit_newtype_struct(self)
     }
 
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        match self.peek().ok_or(Error::EofWhileParsingValue)? {
+        match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
             b'[' => {
                 self.eat_char();
                 let ret = visitor.visit_seq(SeqAccess::new(self))?;
@@ -454,6 +1093,13 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
                 Ok(ret)
             }
+            b'"' if self.config.pack_arrays => {
+                let mut packed = self.parse_packed(false)?;
+                let ret = visitor.visit_seq(&mut packed)?;
+                packed.end()?;
+
+                Ok(ret)
+            }
             _ => Err(Error::InvalidType),
         }
     }
@@ -462,43 +1108,49 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.deserialize_seq(visitor)
+        match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+            b'[' => {
+                self.eat_char();
+                let ret = visitor.visit_seq(SeqAccess::tuple(self))?;
+
+                self.end_seq()?;
+
+                Ok(ret)
+            }
+            b'"' if self.config.pack_arrays => {
+                let mut packed = self.parse_packed(true)?;
+                let ret = visitor.visit_seq(&mut packed)?;
+                packed.end()?;
+
+                Ok(ret)
+            }
+            _ => Err(Error::InvalidType),
+        }
     }
 
     fn deserialize_tuple_struct<V>(
         self,
         _name: &'static str,
-        _len: usize,
-        _visitor: V,
+        len: usize,
+        visitor: V,
     ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        self.deserialize_tuple(len, visitor)
     }
 
-    fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
-    }
-
-    fn deserialize_struct<V>(
-        self,
-        _name: &'static str,
-        _fields: &'static [&'static str],
-        visitor: V,
-    ) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        let peek = self.parse_whitespace().ok_or(Error::EofWhileParsingValue)?;
+        let peek = self.parse_whitespace().ok_or(Error::EofWhileParsingValue)?;
+        let skip_key = self.skip_key.take();
 
         if peek == b'{' {
             self.eat_char();
 
-            let ret = visitor.visit_map(MapAccess::new(self))?;
+            let ret = visitor.visit_map(MapAccess::new(self, skip_key))?;
 
             self.end_map()?;
 
@@ -508,6 +1160,22 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         }
     }
 
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.fields.is_none() {
+            self.fields = Some(fields);
+        }
+
+        self.deserialize_map(visitor)
+    }
+
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
@@ -519,6 +1187,27 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
             b'"' => visitor.visit_enum(UnitVariantAccess::new(self)),
+            b'{' if self.config.enum_tag.is_some() => {
+                let tag = self.config.enum_tag.unwrap();
+                let variant = self.find_tag(tag)?;
+
+                visitor.visit_enum(TaggedVariantAccess::new(self, variant, tag))
+            }
+            b'{' => {
+                self.eat_char();
+
+                match self.parse_whitespace().ok_or(Error::EofWhileParsingObject)? {
+                    b'"' => {}
+                    b'}' => return Err(Error::ExpectedSomeValue),
+                    _ => return Err(Error::KeyMustBeAString),
+                }
+
+                let ret = visitor.visit_enum(VariantAccess::new(self))?;
+
+                self.end_map()?;
+
+                Ok(ret)
+            }
             _ => Err(Error::ExpectedSomeValue),
         }
     }
@@ -530,11 +1219,16 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         self.deserialize_str(visitor)
     }
 
-    fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        self.skip_value()?;
+        visitor.visit_unit()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        !self.config.binary
     }
 }
 
@@ -543,34 +1237,481 @@ impl de::Error for Error {
     where
         T: fmt::Display,
     {
-        unreachable!()
+        Error::Custom
+    }
+
+    fn invalid_type(_unexp: de::Unexpected, _exp: &dyn de::Expected) -> Self {
+        Error::InvalidType
+    }
+
+    fn invalid_value(unexp: de::Unexpected, _exp: &dyn de::Expected) -> Self {
+        match unexp {
+            de::Unexpected::Other(::helpers::INVALID_DURATION) => Error::InvalidDuration,
+            de::Unexpected::Float(_) => Error::InvalidNumber,
+            _ => Error::InvalidType,
+        }
+    }
+
+    // NOTE this is how `heapless::Vec` and `heapless::String` report running out of capacity;
+    // missing tuple elements are reported as `Error::MissingElement` instead, see `SeqAccess`
+    fn invalid_length(_len: usize, _exp: &dyn de::Expected) -> Self {
+        Error::SeqTooLong
+    }
+
+    fn unknown_variant(_variant: &str, _expected: &'static [&'static str]) -> Self {
+        Error::UnknownVariant
+    }
+
+    fn unknown_field(_field: &str, _expected: &'static [&'static str]) -> Self {
+        Error::UnknownField
+    }
+
+    fn missing_field(_field: &'static str) -> Self {
+        Error::MissingField
+    }
+
+    fn duplicate_field(_field: &'static str) -> Self {
+        Error::DuplicateField
     }
 }
 
 impl fmt::Display for Error {
-    fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
-        unreachable!()
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Error::ByteOutOfRange => "byte is out of range",
+            Error::ChecksumMismatch => "CRC is missing or doesn't match",
+            Error::Custom => "custom error",
+            Error::DuplicateField => "duplicate field",
+            Error::EofWhileParsingList => "EOF while parsing a list",
+            Error::EofWhileParsingObject => "EOF while parsing an object",
+            Error::EofWhileParsingString => "EOF while parsing a string",
+            Error::EofWhileParsingValue => "EOF while parsing a JSON value",
+            Error::EscapeInBorrowedStr => "string contains escape sequences and can't be borrowed",
+            Error::EscapedStringTooLong => "unescaped string doesn't fit in the scratch buffer",
+            Error::ExpectedColon => "expected `:`",
+            Error::ExpectedListCommaOrEnd => "expected `,` or `]`",
+            Error::ExpectedObjectCommaOrEnd => "expected `,` or `}`",
+            Error::ExpectedSomeIdent => "expected `true`, `false` or `null`",
+            Error::ExpectedSomeValue => "expected a JSON value",
+            Error::InvalidBase64 => "invalid base64",
+            Error::InvalidDuration => "invalid ISO-8601 duration",
+            Error::InvalidEscape => "invalid escape sequence",
+            Error::InvalidHex => "invalid hex",
+            Error::InvalidNumber => "invalid number",
+            Error::InvalidType => "invalid type",
+            Error::InvalidUnicodeCodePoint => "invalid unicode code point",
+            Error::KeyMustBeAString => "object key must be a string",
+            Error::MissingElement => "array is too short",
+            Error::MissingField => "missing field",
+            Error::SeqTooLong => "array is too long",
+            Error::TooManyUnknownFields => "too many unknown fields",
+            Error::TrailingCharacters => "trailing characters",
+            Error::TrailingComma => "trailing comma",
+            Error::UnknownField => "unknown field",
+            Error::UnknownVariant => "unknown enum variant",
+        })
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Error {
+    fn format(&self, f: defmt::Formatter) {
+        match *self {
+            Error::ByteOutOfRange => defmt::write!(f, "byte is out of range"),
+            Error::ChecksumMismatch => defmt::write!(f, "CRC is missing or doesn't match"),
+            Error::Custom => defmt::write!(f, "custom error"),
+            Error::DuplicateField => defmt::write!(f, "duplicate field"),
+            Error::EofWhileParsingList => defmt::write!(f, "EOF while parsing a list"),
+            Error::EofWhileParsingObject => defmt::write!(f, "EOF while parsing an object"),
+            Error::EofWhileParsingString => defmt::write!(f, "EOF while parsing a string"),
+            Error::EofWhileParsingValue => defmt::write!(f, "EOF while parsing a JSON value"),
+            Error::EscapeInBorrowedStr => {
+                defmt::write!(f, "string contains escape sequences and can't be borrowed")
+            }
+            Error::EscapedStringTooLong => {
+                defmt::write!(f, "unescaped string doesn't fit in the scratch buffer")
+            }
+            Error::ExpectedColon => defmt::write!(f, "expected `:`"),
+            Error::ExpectedListCommaOrEnd => defmt::write!(f, "expected `,` or `]`"),
+            Error::ExpectedObjectCommaOrEnd => defmt::write!(f, "expected `,` or `}}`"),
+            Error::ExpectedSomeIdent => defmt::write!(f, "expected `true`, `false` or `null`"),
+            Error::ExpectedSomeValue => defmt::write!(f, "expected a JSON value"),
+            Error::InvalidBase64 => defmt::write!(f, "invalid base64"),
+            Error::InvalidDuration => defmt::write!(f, "invalid ISO-8601 duration"),
+            Error::InvalidEscape => defmt::write!(f, "invalid escape sequence"),
+            Error::InvalidHex => defmt::write!(f, "invalid hex"),
+            Error::InvalidNumber => defmt::write!(f, "invalid number"),
+            Error::InvalidType => defmt::write!(f, "invalid type"),
+            Error::InvalidUnicodeCodePoint => defmt::write!(f, "invalid unicode code point"),
+            Error::KeyMustBeAString => defmt::write!(f, "object key must be a string"),
+            Error::MissingElement => defmt::write!(f, "array is too short"),
+            Error::MissingField => defmt::write!(f, "missing field"),
+            Error::SeqTooLong => defmt::write!(f, "array is too long"),
+            Error::TooManyUnknownFields => defmt::write!(f, "too many unknown fields"),
+            Error::TrailingCharacters => defmt::write!(f, "trailing characters"),
+            Error::TrailingComma => defmt::write!(f, "trailing comma"),
+            Error::UnknownField => defmt::write!(f, "unknown field"),
+            Error::UnknownVariant => defmt::write!(f, "unknown enum variant"),
+        }
+    }
+}
+
+/// An error along with the position in the input at which it was detected, see
+/// `from_slice_with_position`
+#[derive(Debug, PartialEq)]
+pub struct PositionedError {
+    /// What went wrong
+    pub error: Error,
+    /// Byte offset into the input
+    ///
+    /// This is where the parser stopped: usually the offending character, or the end of the input
+    /// for the `EofWhileParsing*` errors. Errors reported by the `Deserialize` implementation, e.g.
+    /// `Error::MissingField`, point right after the value they refer to.
+    pub position: usize,
+}
+
+impl PositionedError {
+    /// Computes the line and column, both starting at 1, of `position` in `input`
+    ///
+    /// `input` must be the input that produced this error. Columns count bytes, not characters.
+    pub fn line_column(&self, input: &[u8]) -> (usize, usize) {
+        let before = &input[..cmp::min(self.position, input.len())];
+        let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+        let line_start = before
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map(|i| i + 1)
+            .unwrap_or(0);
+
+        (line, before.len() - line_start + 1)
+    }
+}
+
+impl ::core::error::Error for PositionedError {}
+
+impl fmt::Display for PositionedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at byte {}", self.error, self.position)
     }
 }
 
 /// Deserializes an instance of type `T` from bytes of JSON text
-pub fn from_slice<'a, T>(v: &'a [u8]) -> Result<T>
+///
+/// Returns the value and the number of bytes consumed: the JSON value plus the whitespace around
+/// it. Anything after that, e.g. the next value of a stream, is left for the caller. The input is
+/// not validated as UTF-8 up front; only the strings that are deserialized are.
+pub fn from_slice<'a, T>(v: &'a [u8]) -> Result<(T, usize)>
 where
     T: de::Deserialize<'a>,
 {
-    let mut de = Deserializer::new(v);
+    from_slice_with_options(v, DeserializerConfig::default())
+}
+
+/// Deserializes an instance of type `T` from bytes of JSON text using the given configuration
+///
+/// See `from_slice` for the returned number of bytes.
+pub fn from_slice_with_options<'a, T>(
+    v: &'a [u8],
+    config: DeserializerConfig,
+) -> Result<(T, usize)>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut de = Deserializer::new(v, config);
+    let value = de::Deserialize::deserialize(&mut de)?;
+    de.parse_whitespace();
+
+    Ok((value, de.index))
+}
+
+/// Deserializes a JSON value that must span all of `v`, apart from whitespace
+fn from_slice_exact<'a, T>(v: &'a [u8], config: DeserializerConfig) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut de = Deserializer::new(v, config);
+    let value = de::Deserialize::deserialize(&mut de)?;
+    de.end()?;
+
+    Ok(value)
+}
+
+/// Deserializes an instance of type `T` from bytes of JSON text, unescaping strings into `scratch`
+///
+/// Strings without escape sequences are borrowed from `v` as usual. Strings with escape sequences
+/// (`\n`, `\"`, `\u00e9`, surrogate pairs like `\ud83d\ude00`, ...) are unescaped into `scratch`
+/// and handed to the visitor as a transient `&str`, so they can be deserialized into owned types
+/// like `heapless::String<N>` but not into a borrowed `&str`, which still fails with
+/// `Error::EscapeInBorrowedStr`. `scratch` must hold the longest unescaped string; fails with
+/// `Error::EscapedStringTooLong` otherwise. Object keys are unescaped the same way, so e.g.
+/// `{"na\u006de":1}` sets the field `name`, and `"a\"b"` is the key `a"b` of a map with owned keys.
+pub fn from_slice_escaped<'a, T>(v: &'a [u8], scratch: &mut [u8]) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    from_slice_escaped_with_options(v, scratch, DeserializerConfig::default())
+}
+
+/// Deserializes an instance of type `T` from bytes of JSON text using the given configuration,
+/// unescaping strings into `scratch`
+///
+/// See `from_slice_escaped`.
+pub fn from_slice_escaped_with_options<'a, T>(
+    v: &'a [u8],
+    scratch: &mut [u8],
+    config: DeserializerConfig,
+) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut de = Deserializer::new(v, config);
+    de.scratch = Some(scratch);
     let value = de::Deserialize::deserialize(&mut de)?;
     de.end()?;
 
     Ok(value)
 }
 
+/// Deserializes a JSON array from bytes of JSON text into the given slice
+///
+/// Returns the number of elements written to the start of `out`; the rest of `out` is left as is.
+/// Fails with `Error::SeqTooLong` if the array has more elements than `out`.
+pub fn from_slice_into<'a, T>(v: &'a [u8], out: &mut [T]) -> Result<usize>
+where
+    T: de::Deserialize<'a>,
+{
+    from_slice_into_with_options(v, out, DeserializerConfig::default())
+}
+
+/// Deserializes a JSON array from bytes of JSON text into the given slice using the given
+/// configuration
+///
+/// See `from_slice_into`.
+pub fn from_slice_into_with_options<'a, T>(
+    v: &'a [u8],
+    out: &mut [T],
+    config: DeserializerConfig,
+) -> Result<usize>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut de = Deserializer::new(v, config);
+
+    match de.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+        b'[' => de.eat_char(),
+        _ => return Err(Error::InvalidType),
+    }
+
+    let mut len = 0;
+    {
+        let mut seq = SeqAccess::new(&mut de);
+        while let Some(element) = de::SeqAccess::next_element(&mut seq)? {
+            *out.get_mut(len).ok_or(Error::SeqTooLong)? = element;
+            len += 1;
+        }
+    }
+
+    de.end_seq()?;
+    de.end()?;
+
+    Ok(len)
+}
+
+/// Deserializes an instance of type `T` from a JSON object whose last field, `key`, is a CRC of the
+/// rest of the object
+///
+/// This checks the CRC written by the `ser::SerializerConfig::crc_field` option: a CRC-32 (IEEE)
+/// of the input from the opening `{` of the object up to the end of the value in front of the CRC
+/// field, i.e. excluding the whitespace and comma that separate them. E.g. the CRC of
+/// `{"id":7, "crc":3218891522}` covers `{"id":7`. The CRC is checked before deserializing `T`;
+/// fails with `Error::ChecksumMismatch` if it doesn't match, or if the object doesn't end with
+/// the CRC field.
+pub fn from_slice_with_crc<'a, T>(v: &'a [u8], key: &str) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    from_slice_with_crc_with_options(v, key, DeserializerConfig::default())
+}
+
+/// Deserializes an instance of type `T` from a JSON object whose last field, `key`, is a CRC of the
+/// rest of the object, using the given configuration
+///
+/// See `from_slice_with_crc`.
+pub fn from_slice_with_crc_with_options<'a, T>(
+    v: &'a [u8],
+    key: &str,
+    config: DeserializerConfig,
+) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut de = Deserializer::new(v, config);
+    if de.parse_whitespace() != Some(b'{') {
+        return Err(Error::InvalidType);
+    }
+    let start = de.index;
+    de.eat_char();
+
+    // end of the last value seen so far
+    let mut end = de.index;
+    let mut crc = None;
+    if de.parse_whitespace() != Some(b'}') {
+        loop {
+            if crc.is_some() {
+                // the CRC field is not the last one
+                return Err(Error::ChecksumMismatch);
+            }
+
+            match de.parse_whitespace().ok_or(Error::EofWhileParsingObject)? {
+                b'"' => de.eat_char(),
+                _ => return Err(Error::KeyMustBeAString),
+            }
+            let field = de.parse_str()?;
+            de.parse_object_colon()?;
+
+            if field == key {
+                crc = Some(<u32 as de::Deserialize>::deserialize(&mut de)?);
+            } else {
+                de.skip_value()?;
+                end = de.index;
+            }
+
+            match de.parse_whitespace() {
+                Some(b',') => de.eat_char(),
+                _ => break,
+            }
+        }
+    }
+
+    if crc != Some(::ser::crc32(&v[start..end])) {
+        return Err(Error::ChecksumMismatch);
+    }
+
+    from_slice_exact(v, config)
+}
+
+/// Deserializes an instance of type `T` from bytes of JSON text, reporting where in the input an
+/// error was detected
+///
+/// Like `from_str`, this fails if there's anything but whitespace after the value.
+pub fn from_slice_with_position<'a, T>(v: &'a [u8]) -> ::core::result::Result<T, PositionedError>
+where
+    T: de::Deserialize<'a>,
+{
+    from_slice_with_position_with_options(v, DeserializerConfig::default())
+}
+
+/// Deserializes an instance of type `T` from bytes of JSON text using the given configuration,
+/// reporting where in the input an error was detected
+///
+/// See `from_slice_with_position`.
+pub fn from_slice_with_position_with_options<'a, T>(
+    v: &'a [u8],
+    config: DeserializerConfig,
+) -> ::core::result::Result<T, PositionedError>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut de = Deserializer::new(v, config);
+    let ret = de::Deserialize::deserialize(&mut de).and_then(|value| {
+        de.end()?;
+        Ok(value)
+    });
+
+    ret.map_err(|error| PositionedError {
+        error,
+        position: de.index,
+    })
+}
+
+/// Deserializes a struct from bytes of JSON text, also returning the fields of the JSON object that
+/// the struct doesn't have
+///
+/// The unknown fields are returned in input order as pairs of raw JSON text: the key without its
+/// quotes and the value as is, e.g. `("tags", r#"["a","b"]"#)`. Fails with
+/// `Error::TooManyUnknownFields` if they don't fit in the vector.
+pub fn from_slice_with_unknown<'a, T, const N: usize>(
+    v: &'a [u8],
+) -> Result<(T, Vec<(&'a str, &'a str), N>)>
+where
+    T: de::Deserialize<'a>,
+{
+    from_slice_with_unknown_with_options(v, DeserializerConfig::default())
+}
+
+/// Deserializes a struct from bytes of JSON text using the given configuration, also returning
+/// the fields of the JSON object that the struct doesn't have
+///
+/// See `from_slice_with_unknown`.
+pub fn from_slice_with_unknown_with_options<'a, T, const N: usize>(
+    v: &'a [u8],
+    config: DeserializerConfig,
+) -> Result<(T, Vec<(&'a str, &'a str), N>)>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut de = Deserializer::new(v, config);
+    if de.parse_whitespace() != Some(b'{') {
+        return Err(Error::InvalidType);
+    }
+
+    let value = de::Deserialize::deserialize(&mut de)?;
+    de.end()?;
+    let fields = de.fields.unwrap_or(&[]);
+
+    // the input is known to be valid at this point; go over the top level object again
+    let mut de = Deserializer::new(v, config);
+    de.parse_whitespace();
+    de.eat_char();
+
+    let mut unknown = Vec::new();
+    if de.parse_whitespace() != Some(b'}') {
+        loop {
+            de.parse_whitespace();
+            de.eat_char();
+            let key = de.parse_str()?;
+            de.parse_object_colon()?;
+
+            de.parse_whitespace();
+            let start = de.index;
+            de.skip_value()?;
+            // `skip_value` doesn't validate the strings it skips over
+            let raw = str::from_utf8(&v[start..de.index])
+                .map_err(|_| Error::InvalidUnicodeCodePoint)?;
+
+            if !fields.contains(&key) {
+                unknown
+                    .push((key, raw))
+                    .map_err(|_| Error::TooManyUnknownFields)?;
+            }
+
+            match de.parse_whitespace() {
+                Some(b',') => de.eat_char(),
+                _ => break,
+            }
+        }
+    }
+
+    Ok((value, unknown))
+}
+
 /// Deserializes an instance of type T from a string of JSON text
+///
+/// Unlike `from_slice`, this fails with `Error::TrailingCharacters` if there's anything but
+/// whitespace after the value.
 pub fn from_str<'a, T>(s: &'a str) -> Result<T>
 where
     T: de::Deserialize<'a>,
 {
-    from_slice(s.as_bytes())
+    from_str_with_options(s, DeserializerConfig::default())
+}
+
+/// Deserializes an instance of type T from a string of JSON text using the given configuration
+pub fn from_str_with_options<'a, T>(s: &'a str, config: DeserializerConfig) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    from_slice_exact(s.as_bytes(), config)
 }
 
 #[cfg(test)]
@@ -585,6 +1726,39 @@ mod tests {
         Thing,
     }
 
+    #[test]
+    fn allow_underscores() {
+        use super::{DeserializerConfig, Error};
+
+        let config = DeserializerConfig {
+            allow_underscores: true,
+            ..DeserializerConfig::default()
+        };
+
+        assert_eq!(super::from_str_with_options("1_000", config), Ok(1_000u16));
+        assert_eq!(super::from_str_with_options("-1_000_000", config), Ok(-1_000_000i32));
+        assert_eq!(super::from_str_with_options("1_000.000_5", config), Ok(1_000.000_5f64));
+        assert_eq!(super::from_str_with_options("1_0e1_0", config), Ok(1e11f64));
+        assert_eq!(super::from_str_with_options("[1_0,2]", config), Ok([10u8, 2]));
+        assert_eq!(
+            super::from_str_with_options::<::serde_json::Value>("1_000", config)
+                .unwrap()
+                .as_u64(),
+            Some(1_000)
+        );
+
+        // errors
+        assert_eq!(super::from_str_with_options::<u8>("1__0", config), Err(Error::InvalidNumber));
+        assert_eq!(super::from_str_with_options::<u8>("1_", config), Err(Error::InvalidNumber));
+        assert!(super::from_str_with_options::<u8>("_1", config).is_err());
+        assert!(super::from_str_with_options::<f32>("1__0.5", config).is_err());
+        assert!(super::from_str_with_options::<f32>("1._5", config).is_err());
+
+        // strict by default
+        assert_eq!(super::from_str::<u16>("1_000"), Err(Error::TrailingCharacters));
+        assert_eq!(super::from_str::<f32>("1_000"), Err(Error::TrailingCharacters));
+    }
+
     #[test]
     fn array() {
         assert_eq!(super::from_str::<[i32; 0]>("[]"), Ok([]));
@@ -594,6 +1768,25 @@ mod tests {
         assert!(super::from_str::<[i32; 2]>("[0, 1,]").is_err());
     }
 
+    #[test]
+    fn array_of_str() {
+        let input = r#"["a", "b"]"#;
+        let strs: [&str; 2] = super::from_str(input).unwrap();
+
+        assert_eq!(strs, ["a", "b"]);
+
+        // zero copy: the elements point into the input
+        for s in &strs {
+            assert!((s.as_ptr() as usize - input.as_ptr() as usize) < input.len());
+        }
+
+        // escaped strings can't be borrowed
+        assert_eq!(
+            super::from_str::<[&str; 2]>(r#"["a", "b\"c"]"#),
+            Err(super::Error::EscapeInBorrowedStr)
+        );
+    }
+
     #[test]
     fn bool() {
         assert_eq!(super::from_str("true"), Ok(true));
@@ -609,47 +1802,1174 @@ mod tests {
         assert!(super::from_str::<bool>("tru").is_err());
     }
 
+    #[cfg(feature = "std")]
     #[test]
-    fn enum_clike() {
-        assert_eq!(super::from_str(r#" "boolean" "#), Ok(Type::Boolean));
-        assert_eq!(super::from_str(r#" "number" "#), Ok(Type::Number));
-        assert_eq!(super::from_str(r#" "thing" "#), Ok(Type::Thing));
+    fn btree_map() {
+        use std::collections::BTreeMap;
+        use std::string::String;
+
+        let map: BTreeMap<String, u8> = super::from_str(r#"{ "b": 2, "a": 1, "b": 3 }"#).unwrap();
+
+        let mut entries = map.iter();
+        assert_eq!(entries.next(), Some((&String::from("a"), &1)));
+        assert_eq!(entries.next(), Some((&String::from("b"), &3)));
+        assert_eq!(entries.next(), None);
+        // escaped keys are unescaped into the scratch buffer
+        let mut scratch = [0; 8];
+        let map: BTreeMap<String, u8> =
+            super::from_slice_escaped(br#"{"a\"b":1,"\u00e9":2}"#, &mut scratch).unwrap();
+
+        let mut entries = map.iter();
+        assert_eq!(entries.next(), Some((&String::from("a\"b"), &1)));
+        assert_eq!(entries.next(), Some((&String::from("\u{e9}"), &2)));
+        assert_eq!(entries.next(), None);
+
+        // without a scratch buffer they can't be decoded
+        assert_eq!(
+            super::from_str::<BTreeMap<String, u8>>(r#"{"a\"b":1}"#),
+            Err(super::Error::EscapeInBorrowedStr)
+        );
     }
 
     #[test]
-    fn str() {
-        assert_eq!(super::from_str(r#" "hello" "#), Ok("hello"));
+    fn bytes() {
+        use super::{DeserializerConfig, Error};
+        use heapless::Vec;
+        use ser::{BytesFormat, SerializerConfig};
+        use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+
+        #[derive(Debug, PartialEq)]
+        struct Bytes(Vec<u8, 8>);
+
+        impl<'de> Deserialize<'de> for Bytes {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_bytes(BytesVisitor)
+            }
+        }
+
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Bytes;
+
+            fn expecting(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                f.write_str("at most 8 bytes")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Bytes, E>
+            where
+                E: de::Error,
+            {
+                Vec::from_slice(v)
+                    .map(Bytes)
+                    .map_err(|_| E::invalid_length(v.len(), &self))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Bytes, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut bytes = Vec::new();
+                while let Some(byte) = seq.next_element()? {
+                    bytes
+                        .push(byte)
+                        .map_err(|_| de::Error::invalid_length(bytes.len(), &self))?;
+                }
+                Ok(Bytes(bytes))
+            }
+        }
+
+        let bytes = |v: &[u8]| Bytes(Vec::from_slice(v).unwrap());
+
+        assert_eq!(super::from_str("[1,2,255]"), Ok(bytes(&[1, 2, 255])));
+        assert_eq!(super::from_str(" [ ] "), Ok(bytes(&[])));
+        assert_eq!(super::from_str(r#""hi""#), Ok(bytes(b"hi")));
+        assert_eq!(super::from_str::<Bytes>("[256]"), Err(Error::ByteOutOfRange));
+        assert_eq!(super::from_str::<Bytes>("[-1]"), Err(Error::InvalidNumber));
+
+        // borrowed from the input
+        let json = r#""hello""#;
+        let slice: &[u8] = super::from_str(json).unwrap();
+        assert_eq!(slice, b"hello");
+        assert_eq!(slice.as_ptr(), json[1..].as_ptr());
+        // an array can't be borrowed
+        assert_eq!(super::from_str::<&[u8]>("[1]"), Err(Error::InvalidType));
+
+        let config = DeserializerConfig {
+            bytes_base64: true,
+            ..DeserializerConfig::default()
+        };
+        assert_eq!(
+            super::from_str_with_options(r#""AQL/""#, config),
+            Ok(bytes(&[1, 2, 255]))
+        );
+        assert_eq!(
+            super::from_str_with_options(r#"[1,2,255]"#, config),
+            Ok(bytes(&[1, 2, 255]))
+        );
+        assert_eq!(
+            super::from_str_with_options::<Bytes>(r#""AQ*/""#, config),
+            Err(Error::InvalidBase64)
+        );
+
+        let config = DeserializerConfig {
+            bytes_hex: true,
+            ..DeserializerConfig::default()
+        };
+        assert_eq!(
+            super::from_str_with_options(r#""0102fF""#, config),
+            Ok(bytes(&[1, 2, 255]))
+        );
+        assert_eq!(super::from_str_with_options(r#""""#, config), Ok(bytes(&[])));
+        assert_eq!(
+            super::from_str_with_options::<Bytes>(r#""010""#, config),
+            Err(Error::InvalidHex)
+        );
+        assert_eq!(
+            super::from_str_with_options::<Bytes>(r#""0g""#, config),
+            Err(Error::InvalidHex)
+        );
+        // `Bytes` holds at most 8 bytes
+        assert!(super::from_str_with_options::<Bytes>(r#""000102030405060708""#, config).is_err());
+
+        // round trips
+        impl ::serde::Serialize for Bytes {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                serializer.serialize_bytes(&self.0)
+            }
+        }
+
+        for &(bytes_format, bytes_base64, bytes_hex) in &[
+            (BytesFormat::Base64, true, false),
+            (BytesFormat::Hex, false, true),
+        ] {
+            let ser_config = SerializerConfig {
+                bytes_format,
+                ..SerializerConfig::default()
+            };
+            let config = DeserializerConfig {
+                bytes_base64,
+                bytes_hex,
+                ..DeserializerConfig::default()
+            };
+
+            for v in &[&b""[..], b"\x01", b"\x00\xff\x10", b"\x00\xff\x10\xab\x01"] {
+                let json = ::ser::to_string_with_options::<32, _>(&bytes(v), ser_config).unwrap();
+                assert_eq!(super::from_str_with_options(&json, config), Ok(bytes(v)));
+            }
+        }
     }
 
     #[test]
-    fn struct_bool() {
+    fn char() {
+        use super::Error;
+
+        assert_eq!(super::from_str(r#""x""#), Ok('x'));
+        assert_eq!(super::from_str(r#""é""#), Ok('é'));
+        assert_eq!(super::from_str::<char>(r#""xy""#), Err(Error::InvalidType));
+        assert_eq!(super::from_str::<char>("1"), Err(Error::InvalidType));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn cow_str() {
+        use std::borrow::Cow;
+
         #[derive(Debug, Deserialize, PartialEq)]
-        struct Led {
-            led: bool,
+        struct Name<'a> {
+            #[serde(borrow)]
+            name: Cow<'a, str>,
         }
 
-        assert_eq!(super::from_str(r#"{ "led": true }"#), Ok(Led { led: true }));
+        match super::from_str::<Name>(r#"{ "name": "abc" }"#).unwrap().name {
+            Cow::Borrowed(name) => assert_eq!(name, "abc"),
+            Cow::Owned(_) => panic!("expected a borrowed string"),
+        }
+
+        // escaped strings are unescaped into the scratch buffer and copied
+        let mut scratch = [0; 8];
+        let json = br#"{ "name": "a\nb" }"#;
+        match super::from_slice_escaped::<Name>(json, &mut scratch).unwrap().name {
+            Cow::Borrowed(_) => panic!("expected an owned string"),
+            Cow::Owned(name) => assert_eq!(name, "a\nb"),
+        }
+
+        // which needs a scratch buffer
         assert_eq!(
-            super::from_str(r#"{ "led": false }"#),
-            Ok(Led { led: false })
+            super::from_str::<Name>(r#"{ "name": "a\nb" }"#),
+            Err(super::Error::EscapeInBorrowedStr)
         );
     }
 
     #[test]
-    fn struct_i8() {
+    fn crc() {
+        use super::Error;
+
         #[derive(Debug, Deserialize, PartialEq)]
-        struct Temperature {
-            temperature: i8,
+        struct Reading {
+            id: u8,
+            temperature: f32,
         }
 
         assert_eq!(
-            super::from_str(r#"{ "temperature": -17 }"#),
-            Ok(Temperature { temperature: -17 })
+            super::from_slice_with_crc(br#"{"id":7,"temperature":21.5,"crc":3221871975}"#, "crc"),
+            Ok(Reading {
+                id: 7,
+                temperature: 21.5,
+            })
         );
 
+        // whitespace in front of the comma is not covered
         assert_eq!(
-            super::from_str(r#"{ "temperature": -0 }"#),
-            Ok(Temperature { temperature: -0 })
+            super::from_slice_with_crc::<Reading>(
+                br#"{"id":7,"temperature":21.5 , "crc":3221871975}"#,
+                "crc"
+            ).map(|reading| reading.id),
+            Ok(7)
+        );
+
+        // tampered
+        assert_eq!(
+            super::from_slice_with_crc::<Reading>(
+                br#"{"id":7,"temperature":31.5,"crc":3221871975}"#,
+                "crc"
+            ),
+            Err(Error::ChecksumMismatch)
+        );
+
+        // missing or not last
+        assert_eq!(
+            super::from_slice_with_crc::<Reading>(br#"{"id":7,"temperature":21.5}"#, "crc"),
+            Err(Error::ChecksumMismatch)
+        );
+        assert_eq!(
+            super::from_slice_with_crc::<Reading>(
+                br#"{"crc":3221871975,"id":7,"temperature":21.5}"#,
+                "crc"
+            ),
+            Err(Error::ChecksumMismatch)
+        );
+    }
+
+    #[test]
+    fn empty() {
+        use super::Error;
+
+        assert_eq!(super::from_str::<()>("null"), Ok(()));
+        assert_eq!(super::from_str::<()>(" null "), Ok(()));
+        assert_eq!(super::from_str::<&str>(r#""""#), Ok(""));
+        #[cfg(feature = "std")]
+        assert_eq!(
+            super::from_str::<::std::string::String>(r#""""#),
+            Ok(::std::string::String::new())
+        );
+
+        // an empty string is a value; an empty document is not
+        assert_eq!(super::from_str::<u8>(""), Err(Error::EofWhileParsingValue));
+        assert_eq!(super::from_str::<&str>(""), Err(Error::EofWhileParsingValue));
+        assert_eq!(super::from_str::<()>("  "), Err(Error::EofWhileParsingValue));
+        assert_eq!(
+            super::from_str::<&str>(r#"""#),
+            Err(Error::EofWhileParsingString)
+        );
+
+        // errors
+        assert_eq!(super::from_str::<()>("nul"), Err(Error::ExpectedSomeIdent));
+        assert_eq!(super::from_str::<()>("0"), Err(Error::InvalidType));
+    }
+
+    #[test]
+    fn enum_clike() {
+        assert_eq!(super::from_str(r#" "boolean" "#), Ok(Type::Boolean));
+        assert_eq!(super::from_str(r#" "number" "#), Ok(Type::Number));
+        assert_eq!(super::from_str(r#" "thing" "#), Ok(Type::Thing));
+    }
+
+    #[test]
+    fn enum_externally_tagged() {
+        use super::Error;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Command {
+            Ping,
+            Brightness(u8),
+            Color(u8, u8, u8),
+            Move { x: i32, y: i32 },
+        }
+
+        assert_eq!(super::from_str(r#""Ping""#), Ok(Command::Ping));
+        assert_eq!(
+            super::from_str(r#"{"Brightness":128}"#),
+            Ok(Command::Brightness(128))
+        );
+        assert_eq!(
+            super::from_str(r#" { "Color" : [255, 0, 128] } "#),
+            Ok(Command::Color(255, 0, 128))
+        );
+        assert_eq!(
+            super::from_str(r#"{"Move":{"y":2,"x":-1}}"#),
+            Ok(Command::Move { x: -1, y: 2 })
+        );
+
+        // errors
+        assert_eq!(super::from_str::<Command>(r#""Pong""#), Err(Error::UnknownVariant));
+        assert_eq!(
+            super::from_str::<Command>(r#"{"Jump":1}"#),
+            Err(Error::UnknownVariant)
+        );
+        assert_eq!(super::from_str::<Command>(r#"{"Brightness":true}"#), Err(Error::InvalidType));
+        assert_eq!(super::from_str::<Command>(r#""Brightness""#), Err(Error::InvalidType));
+        assert!(super::from_str::<Command>(r#"{"Brightness":1,"Ping":null}"#).is_err());
+    }
+
+    // NOTE serde buffers the fields that precede the tag, which requires `std`
+    #[cfg(feature = "std")]
+    #[test]
+    fn enum_internally_tagged() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(tag = "type")]
+        enum Message {
+            A { x: u8 },
+            B { y: bool, z: i8 },
+        }
+
+        assert_eq!(
+            super::from_str(r#"{"type":"A","x":1}"#),
+            Ok(Message::A { x: 1 })
+        );
+        assert_eq!(
+            super::from_str(r#"{ "y": true, "z": -3, "type": "B" }"#),
+            Ok(Message::B { y: true, z: -3 })
+        );
+    }
+
+    #[test]
+    fn enum_lenient() {
+        use super::{DeserializerConfig, Error};
+
+        let config = DeserializerConfig {
+            lenient_enum: true,
+            ..DeserializerConfig::default()
+        };
+
+        assert_eq!(
+            super::from_str_with_options(r#""number""#, config),
+            Ok(Type::Number)
+        );
+        assert_eq!(
+            super::from_str_with_options(r#"{"number":null}"#, config),
+            Ok(Type::Number)
+        );
+        assert_eq!(
+            super::from_str_with_options(r#" { "number" : null } "#, config),
+            Ok(Type::Number)
+        );
+
+        // errors
+        assert_eq!(
+            super::from_str_with_options::<Type>(r#"{"number":1}"#, config),
+            Err(Error::InvalidType)
+        );
+        assert_eq!(
+            super::from_str_with_options::<Type>("{}", config),
+            Err(Error::ExpectedSomeValue)
+        );
+        assert!(
+            super::from_str_with_options::<Type>(r#"{"number":null,"thing":null}"#, config)
+                .is_err()
+        );
+
+        // strict by default
+        assert_eq!(
+            super::from_str::<Type>(r#"{"number":null}"#),
+            Err(Error::InvalidType)
+        );
+    }
+
+    #[test]
+    fn enum_tag() {
+        use super::{DeserializerConfig, Error};
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Position {
+            x: i8,
+            y: i8,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Message {
+            A { x: u8 },
+            B { y: bool, z: i8 },
+            C,
+            D(Position),
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(deny_unknown_fields)]
+        enum Strict {
+            A { x: u8 },
+        }
+
+        let config = DeserializerConfig {
+            enum_tag: Some("type"),
+            ..DeserializerConfig::default()
+        };
+
+        assert_eq!(
+            super::from_str_with_options(r#"{"type":"A","x":1}"#, config),
+            Ok(Message::A { x: 1 })
+        );
+        // the tag doesn't have to come first
+        assert_eq!(
+            super::from_str_with_options(r#"{ "y": true, "z": -3, "type": "B" }"#, config),
+            Ok(Message::B { y: true, z: -3 })
+        );
+        assert_eq!(
+            super::from_str_with_options(r#"{"type":"C"}"#, config),
+            Ok(Message::C)
+        );
+        assert_eq!(super::from_str_with_options(r#""C""#, config), Ok(Message::C));
+        assert_eq!(
+            super::from_str_with_options(r#"{"x":1,"type":"D","y":2}"#, config),
+            Ok(Message::D(Position { x: 1, y: 2 }))
+        );
+        // the tag is not an unknown field of the variant
+        assert_eq!(
+            super::from_str_with_options(r#"{"type":"A","x":1}"#, config),
+            Ok(Strict::A { x: 1 })
+        );
+
+        assert_eq!(
+            super::from_str_with_options::<Message>(r#"{"x":1}"#, config),
+            Err(Error::MissingField)
+        );
+        assert_eq!(
+            super::from_str_with_options::<Message>(r#"{"type":"E"}"#, config),
+            Err(Error::UnknownVariant)
+        );
+        assert_eq!(
+            super::from_str_with_options::<Message>(r#"{"type":1}"#, config),
+            Err(Error::InvalidType)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn error_display() {
+        use super::Error;
+
+        assert_eq!(format!("{}", Error::ByteOutOfRange), "byte is out of range");
+        assert_eq!(format!("{}", Error::ChecksumMismatch), "CRC is missing or doesn't match");
+        assert_eq!(format!("{}", Error::Custom), "custom error");
+        assert_eq!(format!("{}", Error::DuplicateField), "duplicate field");
+        assert_eq!(format!("{}", Error::EofWhileParsingList), "EOF while parsing a list");
+        assert_eq!(format!("{}", Error::EofWhileParsingObject), "EOF while parsing an object");
+        assert_eq!(format!("{}", Error::EofWhileParsingString), "EOF while parsing a string");
+        assert_eq!(format!("{}", Error::EofWhileParsingValue), "EOF while parsing a JSON value");
+        assert_eq!(
+            format!("{}", Error::EscapeInBorrowedStr),
+            "string contains escape sequences and can't be borrowed"
+        );
+        assert_eq!(
+            format!("{}", Error::EscapedStringTooLong),
+            "unescaped string doesn't fit in the scratch buffer"
+        );
+        assert_eq!(format!("{}", Error::ExpectedColon), "expected `:`");
+        assert_eq!(format!("{}", Error::ExpectedListCommaOrEnd), "expected `,` or `]`");
+        assert_eq!(format!("{}", Error::ExpectedObjectCommaOrEnd), "expected `,` or `}`");
+        assert_eq!(format!("{}", Error::ExpectedSomeIdent), "expected `true`, `false` or `null`");
+        assert_eq!(format!("{}", Error::ExpectedSomeValue), "expected a JSON value");
+        assert_eq!(format!("{}", Error::InvalidBase64), "invalid base64");
+        assert_eq!(format!("{}", Error::InvalidDuration), "invalid ISO-8601 duration");
+        assert_eq!(format!("{}", Error::InvalidEscape), "invalid escape sequence");
+        assert_eq!(format!("{}", Error::InvalidHex), "invalid hex");
+        assert_eq!(format!("{}", Error::InvalidNumber), "invalid number");
+        assert_eq!(format!("{}", Error::InvalidType), "invalid type");
+        assert_eq!(format!("{}", Error::InvalidUnicodeCodePoint), "invalid unicode code point");
+        assert_eq!(format!("{}", Error::KeyMustBeAString), "object key must be a string");
+        assert_eq!(format!("{}", Error::MissingElement), "array is too short");
+        assert_eq!(format!("{}", Error::MissingField), "missing field");
+        assert_eq!(format!("{}", Error::SeqTooLong), "array is too long");
+        assert_eq!(format!("{}", Error::TooManyUnknownFields), "too many unknown fields");
+        assert_eq!(format!("{}", Error::TrailingCharacters), "trailing characters");
+        assert_eq!(format!("{}", Error::TrailingComma), "trailing comma");
+        assert_eq!(format!("{}", Error::UnknownField), "unknown field");
+        assert_eq!(format!("{}", Error::UnknownVariant), "unknown enum variant");
+
+        let err = super::PositionedError {
+            error: Error::InvalidNumber,
+            position: 42,
+        };
+        assert_eq!(format!("{}", err), "invalid number at byte 42");
+    }
+
+    #[test]
+    fn float() {
+        assert_eq!(super::from_str(" 20.5 "), Ok(20.5f32));
+        assert_eq!(super::from_str("-0.1"), Ok(-0.1f64));
+        assert_eq!(super::from_str("1E3"), Ok(1000f64));
+        assert_eq!(super::from_str("1e+3"), Ok(1000f64));
+        assert_eq!(super::from_str("7"), Ok(7f64));
+        assert_eq!(super::from_str("1.25"), Ok(1.25f32));
+        assert_eq!(
+            super::from_str::<f64>("2.2250738585072011e-308").map(f64::to_bits),
+            Ok(0x000f_ffff_ffff_ffff)
+        );
+
+        // errors
+        assert!(super::from_str::<f64>("+1").is_err());
+        assert!(super::from_str::<f64>(".5").is_err());
+        assert!(super::from_str::<f64>("1.").is_err());
+        assert!(super::from_str::<f64>("1e").is_err());
+        assert!(super::from_str::<f64>("01").is_err());
+        assert!(super::from_str::<f64>("1e999").is_err());
+        assert!(super::from_str::<f64>("true").is_err());
+    }
+
+    #[test]
+    fn float_round_trip() {
+        use core::{f32, f64};
+
+        for &v in &[
+            0.,
+            -0.,
+            1.,
+            0.1,
+            1e-5,
+            1e16,
+            123.456,
+            -1e-7,
+            f64::EPSILON,
+            f64::MIN_POSITIVE,
+            // largest and smallest subnormals
+            f64::from_bits(0x000f_ffff_ffff_ffff),
+            5e-324,
+            f64::MAX,
+            f64::MIN,
+        ] {
+            let json = ::ser::to_string::<32, _>(&v).unwrap();
+            let parsed: f64 = super::from_str(&json).unwrap();
+            assert_eq!(v.to_bits(), parsed.to_bits(), "{}", &*json);
+        }
+
+        for &v in &[
+            0.,
+            -0.,
+            0.1,
+            -20.5,
+            f32::EPSILON,
+            f32::MIN_POSITIVE,
+            1e-45,
+            f32::MAX,
+            f32::MIN,
+        ] {
+            let json = ::ser::to_string::<32, _>(&v).unwrap();
+            let parsed: f32 = super::from_str(&json).unwrap();
+            assert_eq!(v.to_bits(), parsed.to_bits(), "{}", &*json);
+        }
+    }
+
+    #[test]
+    fn from_slice() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Reading {
+            id: u8,
+            temperature: f32,
+        }
+
+        let json = br#"{"id":7,"temperature":21.5}"#;
+        assert_eq!(
+            super::from_slice(json),
+            Ok((
+                Reading {
+                    id: 7,
+                    temperature: 21.5,
+                },
+                json.len()
+            ))
+        );
+
+        // a stream of values; the whitespace in between is consumed
+        let stream = b"{\"id\":1,\"temperature\":0}\n{\"id\":2,\"temperature\":-1}";
+        let (first, len) = super::from_slice::<Reading>(stream).unwrap();
+        assert_eq!((first.id, len), (1, 25));
+        let (second, len) = super::from_slice::<Reading>(&stream[25..]).unwrap();
+        assert_eq!((second.id, len), (2, stream.len() - 25));
+
+        assert_eq!(super::from_slice::<u8>(b" 12 ,"), Ok((12, 4)));
+    }
+
+    #[test]
+    fn from_slice_into() {
+        use super::Error;
+
+        let mut out = [0u8; 4];
+        assert_eq!(super::from_slice_into(b"[1,2,3]", &mut out), Ok(3));
+        assert_eq!(out, [1, 2, 3, 0]);
+
+        assert_eq!(super::from_slice_into(b" [ ] ", &mut out), Ok(0));
+
+        let mut out = [(0u8, false); 2];
+        assert_eq!(
+            super::from_slice_into(b"[[1,true],[2,false]]", &mut out),
+            Ok(2)
+        );
+        assert_eq!(out, [(1, true), (2, false)]);
+
+        // errors
+        let mut out = [0u8; 2];
+        assert_eq!(
+            super::from_slice_into(b"[1,2,3]", &mut out),
+            Err(Error::SeqTooLong)
+        );
+        assert_eq!(
+            super::from_slice_into(b"{}", &mut out),
+            Err(Error::InvalidType)
+        );
+        assert_eq!(
+            super::from_slice_into(b"[1,2]x", &mut out),
+            Err(Error::TrailingCharacters)
+        );
+    }
+
+    #[test]
+    fn heapless() {
+        use super::Error;
+        use heapless::{String, Vec};
+
+        let v: Vec<u8, 4> = super::from_str("[1,2,3]").unwrap();
+        assert_eq!(&*v, &[1, 2, 3]);
+        assert_eq!(super::from_str::<Vec<u8, 2>>("[1,2,3]"), Err(Error::SeqTooLong));
+        assert_eq!(super::from_str::<Vec<u8, 2>>("[]").map(|v| v.len()), Ok(0));
+
+        let s: String<8> = super::from_str(r#""hello""#).unwrap();
+        assert_eq!(&*s, "hello");
+        assert_eq!(super::from_str::<String<4>>(r#""hello""#), Err(Error::SeqTooLong));
+    }
+
+    #[test]
+    fn heapless_structs() {
+        use super::{Error, PositionedError};
+        use heapless::Vec;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Point {
+            x: i8,
+        }
+
+        let json = br#"[{"x":1},{"x":2}]"#;
+
+        let (points, len): (Vec<Point, 2>, _) = super::from_slice(json).unwrap();
+        assert_eq!(&*points, &[Point { x: 1 }, Point { x: 2 }]);
+        assert_eq!(len, json.len());
+
+        // the error points right after the element that doesn't fit
+        let err = super::from_slice_with_position::<Vec<Point, 1>>(json).unwrap_err();
+        assert_eq!(
+            err,
+            PositionedError {
+                error: Error::SeqTooLong,
+                position: 16,
+            }
+        );
+        assert!(json[..err.position].ends_with(br#"{"x":2}"#));
+    }
+
+    #[test]
+    fn integer() {
+        use super::Error;
+
+        assert_eq!(super::from_str("-128"), Ok(-128i8));
+        assert_eq!(super::from_str(" 127 "), Ok(127i8));
+        assert_eq!(super::from_str("255"), Ok(255u8));
+        assert_eq!(super::from_str("-2147483648"), Ok(-2147483648i32));
+        assert_eq!(super::from_str("18446744073709551615"), Ok(18446744073709551615u64));
+
+        // errors
+        assert_eq!(super::from_str::<i8>("-129"), Err(Error::InvalidNumber));
+        assert_eq!(super::from_str::<u8>("256"), Err(Error::InvalidNumber));
+        assert_eq!(super::from_str::<u8>("-1"), Err(Error::InvalidNumber));
+        assert_eq!(super::from_str::<u8>(""), Err(Error::EofWhileParsingValue));
+        assert!(super::from_str::<u8>("1.5").is_err());
+        assert!(super::from_str::<u8>("true").is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn ip_binary() {
+        use std::net::{Ipv4Addr, Ipv6Addr};
+
+        use super::DeserializerConfig;
+        use ser::{self, SerializerConfig};
+
+        let ser_config = SerializerConfig {
+            binary: true,
+            ..SerializerConfig::default()
+        };
+        let de_config = DeserializerConfig {
+            binary: true,
+            ..DeserializerConfig::default()
+        };
+
+        let ip = Ipv4Addr::new(127, 0, 0, 1);
+
+        // human readable by default
+        let json = ser::to_string::<64, _>(&ip).unwrap();
+        assert_eq!(&*json, r#""127.0.0.1""#);
+        assert_eq!(super::from_str(&json), Ok(ip));
+
+        let json = ser::to_string_with_options::<64, _>(&ip, ser_config).unwrap();
+        assert_eq!(&*json, "[127,0,0,1]");
+        assert_eq!(super::from_str_with_options(&json, de_config), Ok(ip));
+
+        let ip = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+
+        let json = ser::to_string_with_options::<64, _>(&ip, ser_config).unwrap();
+        assert_eq!(&*json, "[254,128,0,0,0,0,0,0,0,0,0,0,0,0,0,1]");
+        assert_eq!(super::from_str_with_options(&json, de_config), Ok(ip));
+
+        // the two modes don't mix
+        assert!(super::from_str::<Ipv4Addr>("[127,0,0,1]").is_err());
+    }
+
+    #[test]
+    fn newtype_struct() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Celsius(f32);
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Reading {
+            id: u8,
+            temperature: Celsius,
+        }
+
+        assert_eq!(super::from_str("12.5"), Ok(Celsius(12.5)));
+        assert_eq!(
+            super::from_str(r#"{"id":1,"temperature":-4.5}"#),
+            Ok(Reading {
+                id: 1,
+                temperature: Celsius(-4.5),
+            })
+        );
+    }
+
+    #[test]
+    fn non_finite() {
+        use super::{DeserializerConfig, Error};
+        use ser::{self, NonFinitePolicy, SerializerConfig};
+
+        #[derive(Debug, Deserialize, PartialEq, Serialize)]
+        struct Sample {
+            #[serde(rename = "Infinity")]
+            value: f64,
+        }
+
+        let ser_config = SerializerConfig {
+            non_finite: NonFinitePolicy::AsLiteral,
+            ..SerializerConfig::default()
+        };
+        let de_config = DeserializerConfig {
+            allow_non_finite: true,
+            ..DeserializerConfig::default()
+        };
+
+        for v in &[f64::INFINITY, f64::NEG_INFINITY, 1.5] {
+            let sample = Sample { value: *v };
+            let json = ser::to_string_with_options::<32, _>(&sample, ser_config).unwrap();
+            assert_eq!(super::from_str_with_options(&json, de_config), Ok(sample));
+        }
+
+        assert_eq!(
+            super::from_str_with_options(r#"{"Infinity":-Infinity}"#, de_config),
+            Ok(Sample {
+                value: f64::NEG_INFINITY
+            })
+        );
+
+        let json =
+            ser::to_string_with_options::<32, _>(&f32::NAN, ser_config).unwrap();
+        assert_eq!(&*json, "NaN");
+        assert!(super::from_str_with_options::<f32>(&json, de_config).unwrap().is_nan());
+
+        // errors
+        assert_eq!(
+            super::from_str_with_options::<f32>(r#""NaN""#, de_config),
+            Err(Error::InvalidType)
+        );
+        assert_eq!(
+            super::from_str_with_options::<f32>("Infinityx", de_config),
+            Err(Error::TrailingCharacters)
+        );
+
+        // strict by default
+        assert_eq!(super::from_str::<f32>("NaN"), Err(Error::InvalidNumber));
+        assert_eq!(super::from_str::<f32>("Infinity"), Err(Error::InvalidNumber));
+        assert_eq!(super::from_str::<f32>("-Infinity"), Err(Error::InvalidNumber));
+    }
+
+    #[test]
+    fn option() {
+        assert_eq!(super::from_str("null"), Ok(None::<bool>));
+        assert_eq!(super::from_str(" true "), Ok(Some(true)));
+        assert_eq!(super::from_str("-128"), Ok(Some(-128i8)));
+        assert_eq!(super::from_str("1.25"), Ok(Some(1.25f64)));
+
+        // errors
+        assert!(super::from_str::<Option<u8>>("nul").is_err());
+    }
+
+    #[test]
+    fn pack_arrays() {
+        use super::{DeserializerConfig, Error};
+        use ser::{self, SerializerConfig};
+
+        let config = DeserializerConfig {
+            pack_arrays: true,
+            ..DeserializerConfig::default()
+        };
+
+        let pixels = [0u8, 1, 2, 127, 128, 255];
+        let json = ser::to_string_with_options::<32, _>(
+            &pixels,
+            SerializerConfig {
+                pack_arrays: true,
+                ..SerializerConfig::default()
+            },
+        ).unwrap();
+        assert_eq!(super::from_str_with_options(&json, config), Ok(pixels));
+
+        assert_eq!(
+            super::from_str_with_options(r#""u16:EjT//w==""#, config),
+            Ok([0x1234u16, 0xffff])
+        );
+        // arrays written as usual are still accepted
+        assert_eq!(super::from_str_with_options("[1,2]", config), Ok([1u8, 2]));
+
+        // errors
+        assert_eq!(
+            super::from_str_with_options::<[u8; 2]>(r#""u8:AQID""#, config),
+            Err(Error::SeqTooLong)
+        );
+        assert_eq!(
+            super::from_str_with_options::<[u8; 4]>(r#""u8:AQID""#, config),
+            Err(Error::MissingElement)
+        );
+        assert_eq!(
+            super::from_str_with_options::<[u8; 3]>(r#""u8:AQI!""#, config),
+            Err(Error::InvalidBase64)
+        );
+        assert_eq!(
+            super::from_str_with_options::<[u16; 2]>(r#""u16:AQID""#, config),
+            Err(Error::InvalidBase64)
+        );
+        assert_eq!(
+            super::from_str_with_options::<[u8; 3]>(r#""i8:AQID""#, config),
+            Err(Error::InvalidType)
+        );
+        assert_eq!(super::from_str::<[u8; 3]>(r#""u8:AQID""#), Err(Error::InvalidType));
+    }
+
+    #[test]
+    fn position() {
+        use super::{DeserializerConfig, Error, PositionedError};
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Led {
+            led: bool,
+            rgb: u8,
+        }
+
+        assert_eq!(
+            super::from_slice_with_position(br#"{"led":true,"rgb":1}"#),
+            Ok(Led { led: true, rgb: 1 })
+        );
+
+        let json = br#"{"led": x}"#;
+        let err = super::from_slice_with_position::<Led>(json).unwrap_err();
+        assert_eq!(
+            err,
+            PositionedError {
+                error: Error::InvalidType,
+                position: 8,
+            }
+        );
+        assert_eq!(json[err.position], b'x');
+        assert_eq!(err.line_column(json), (1, 9));
+
+        let json = b"{\n  \"led\": true,\n  \"rgb\" 1\n}";
+        let err = super::from_slice_with_position::<Led>(json).unwrap_err();
+        assert_eq!(err.error, Error::ExpectedColon);
+        assert_eq!(err.position, 25);
+        assert_eq!(err.line_column(json), (3, 9));
+
+        let json = br#"{"led":true,"rgb":1} 2"#;
+        let err = super::from_slice_with_position::<Led>(json).unwrap_err();
+        assert_eq!(err.error, Error::TrailingCharacters);
+        assert_eq!(err.position, 21);
+
+        let config = DeserializerConfig {
+            parse_stringified_numbers: true,
+            ..DeserializerConfig::default()
+        };
+        let json = br#"{"led":true,"rgb":"1"}"#;
+        assert_eq!(
+            super::from_slice_with_position::<Led>(json).map_err(|err| err.error),
+            Err(Error::InvalidType)
+        );
+        assert_eq!(
+            super::from_slice_with_position_with_options(json, config),
+            Ok(Led { led: true, rgb: 1 })
+        );
+    }
+
+    #[test]
+    fn str() {
+        assert_eq!(super::from_str(r#" "hello" "#), Ok("hello"));
+    }
+
+    #[test]
+    fn str_borrowed() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Device<'a> {
+            name: &'a str,
+        }
+
+        let json = r#"{"name":"hello"}"#;
+        let device: Device = super::from_str(json).unwrap();
+        assert_eq!(device.name, "hello");
+        // no copy; the field points into the input
+        assert_eq!(device.name.as_ptr(), json[9..].as_ptr());
+
+        let json = r#""hello""#;
+        let s: &str = super::from_str(json).unwrap();
+        assert_eq!(s.as_ptr(), json[1..].as_ptr());
+
+        assert_eq!(
+            super::from_str::<Device>(r#"{"name":"a\"b"}"#),
+            Err(super::Error::EscapeInBorrowedStr)
+        );
+    }
+
+    #[test]
+    fn str_escaped() {
+        use super::Error;
+        use heapless::String;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Device<'a> {
+            name: &'a str,
+        }
+
+        let mut scratch = [0; 16];
+
+        let s: String<16> = super::from_slice_escaped(br#""a\nb""#, &mut scratch).unwrap();
+        assert_eq!(s, "a\nb");
+
+        let s: String<16> = super::from_slice_escaped(br#""\ud83d\ude00""#, &mut scratch).unwrap();
+        assert_eq!(s, "\u{1f600}");
+
+        // no escapes; borrowed as usual
+        let s: &str = super::from_slice_escaped(br#""hello""#, &mut scratch).unwrap();
+        assert_eq!(s, "hello");
+
+        // lone high surrogate
+        assert_eq!(
+            super::from_slice_escaped::<String<16>>(br#""\ud83d""#, &mut scratch),
+            Err(Error::InvalidEscape)
+        );
+        // truncated escape
+        assert_eq!(
+            super::from_slice_escaped::<String<16>>(br#""\u12""#, &mut scratch),
+            Err(Error::InvalidEscape)
+        );
+        assert_eq!(
+            super::from_slice_escaped::<Device>(br#"{"name":"a\"b"}"#, &mut scratch),
+            Err(Error::EscapeInBorrowedStr)
+        );
+        assert_eq!(
+            super::from_slice_escaped::<String<16>>(br#""a\nb""#, &mut [0; 2]),
+            Err(Error::EscapedStringTooLong)
+        );
+    }
+
+    #[test]
+    fn str_non_ascii() {
+        assert_eq!(super::from_str(r#""température""#), Ok("température"));
+        assert_eq!(super::from_str(r#""25 °C""#), Ok("25 °C"));
+
+        // invalid UTF-8
+        assert_eq!(
+            super::from_slice::<&str>(b"\"caf\xe9\""),
+            Err(super::Error::InvalidUnicodeCodePoint)
+        );
+    }
+
+    #[test]
+    fn stringified_numbers() {
+        use super::{DeserializerConfig, Error};
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Counter {
+            count: u8,
+        }
+
+        let config = DeserializerConfig {
+            parse_stringified_numbers: true,
+            ..DeserializerConfig::default()
+        };
+
+        assert_eq!(
+            super::from_str_with_options(r#"{ "count": "42" }"#, config),
+            Ok(Counter { count: 42 })
+        );
+        assert_eq!(
+            super::from_str_with_options(r#"{ "count": 42 }"#, config),
+            Ok(Counter { count: 42 })
+        );
+        assert_eq!(super::from_str_with_options(r#""-17""#, config), Ok(-17i8));
+        assert_eq!(super::from_str_with_options(r#""2.5""#, config), Ok(2.5f32));
+
+        // errors
+        for json in &[
+            r#"{ "count": "4x" }"#,
+            r#"{ "count": "x" }"#,
+            r#"{ "count": "" }"#,
+            r#"{ "count": " 42" }"#,
+            r#"{ "count": "256" }"#,
+        ] {
+            assert_eq!(
+                super::from_str_with_options::<Counter>(json, config),
+                Err(Error::InvalidNumber)
+            );
+        }
+
+        // strict by default
+        assert_eq!(
+            super::from_str::<Counter>(r#"{ "count": "42" }"#),
+            Err(Error::InvalidType)
+        );
+    }
+
+    #[test]
+    fn struct_alias() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Temperature {
+            #[serde(alias = "temp", alias = "t")]
+            temperature: i8,
+        }
+
+        assert_eq!(
+            super::from_str(r#"{ "temperature": 20 }"#),
+            Ok(Temperature { temperature: 20 })
+        );
+        assert_eq!(
+            super::from_str(r#"{ "temp": 20 }"#),
+            Ok(Temperature { temperature: 20 })
+        );
+        assert_eq!(
+            super::from_str(r#"{ "t": 20 }"#),
+            Ok(Temperature { temperature: 20 })
+        );
+    }
+
+    #[test]
+    fn struct_bool() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Led {
+            led: bool,
+        }
+
+        assert_eq!(super::from_str(r#"{ "led": true }"#), Ok(Led { led: true }));
+        assert_eq!(
+            super::from_str(r#"{ "led": false }"#),
+            Ok(Led { led: false })
+        );
+    }
+
+    #[test]
+    fn struct_f32() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Temperature {
+            temperature: f32,
+        }
+
+        assert_eq!(
+            super::from_str(r#"{ "temperature": -17.5 }"#),
+            Ok(Temperature { temperature: -17.5 })
+        );
+
+        assert_eq!(
+            super::from_str(r#"{ "temperature": 2.5e1 }"#),
+            Ok(Temperature { temperature: 25. })
+        );
+
+        assert!(super::from_str::<Temperature>(r#"{ "temperature": "20" }"#).is_err());
+    }
+
+    #[test]
+    fn struct_deny_unknown_fields() {
+        use super::Error;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(deny_unknown_fields)]
+        struct Point {
+            a: u8,
+            b: u8,
+        }
+
+        assert_eq!(super::from_str(r#"{"a":1,"b":2}"#), Ok(Point { a: 1, b: 2 }));
+        assert_eq!(
+            super::from_str::<Point>(r#"{"a":1,"c":3,"b":2}"#),
+            Err(Error::UnknownField)
+        );
+        assert_eq!(
+            super::from_str::<Point>(r#"{"a":1,"a":1,"b":2}"#),
+            Err(Error::DuplicateField)
+        );
+    }
+
+    #[test]
+    fn struct_fields() {
+        use super::Error;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Point {
+            a: u8,
+            b: u8,
+        }
+
+        assert_eq!(super::from_str(r#"{"a":1,"b":2}"#), Ok(Point { a: 1, b: 2 }));
+        assert_eq!(super::from_str(r#"{"b":2,"a":1}"#), Ok(Point { a: 1, b: 2 }));
+        assert_eq!(super::from_str::<Point>(r#"{"a":1}"#), Err(Error::MissingField));
+
+        // unknown fields are skipped, whatever their value
+        assert_eq!(
+            super::from_str(r#"{"a":1,"c":{"d":[true,null]},"b":2,"e":"f"}"#),
+            Ok(Point { a: 1, b: 2 })
+        );
+    }
+
+    #[test]
+    fn struct_i8() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Temperature {
+            temperature: i8,
+        }
+
+        assert_eq!(
+            super::from_str(r#"{ "temperature": -17 }"#),
+            Ok(Temperature { temperature: -17 })
+        );
+
+        assert_eq!(
+            super::from_str(r#"{ "temperature": -0 }"#),
+            Ok(Temperature { temperature: -0 })
         );
 
         assert_eq!(
@@ -707,6 +3027,138 @@ mod tests {
         assert!(super::from_str::<Temperature>(r#"{ "temperature": -1 }"#).is_err());
     }
 
+    #[test]
+    fn tuple() {
+        use super::Error;
+
+        type Reading = (u8, i8, Option<u8>);
+
+        assert_eq!(super::from_str::<Reading>("[1,-2,3]"), Ok((1, -2, Some(3))));
+        assert_eq!(super::from_str::<Reading>("[1,-2,null]"), Ok((1, -2, None)));
+
+        // trailing `Option`s can be omitted
+        assert_eq!(super::from_str::<Reading>("[1,-2]"), Ok((1, -2, None)));
+        assert_eq!(super::from_str::<Reading>("[ 1, -2 ]"), Ok((1, -2, None)));
+
+        // errors
+        assert_eq!(super::from_str::<Reading>("[1]"), Err(Error::MissingElement));
+        assert_eq!(
+            super::from_str::<Reading>("[1,-2,3,4]"),
+            Err(Error::TrailingCharacters)
+        );
+    }
+
+    #[test]
+    fn tuple_struct() {
+        use super::Error;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Rgb(u8, u8, u8);
+
+        assert_eq!(super::from_str("[255, 0, 128]"), Ok(Rgb(255, 0, 128)));
+        assert_eq!(super::from_str::<Rgb>("[255,0]"), Err(Error::MissingElement));
+        assert_eq!(super::from_str::<Rgb>("[255,0,128,1]"), Err(Error::TrailingCharacters));
+        assert_eq!(super::from_str::<Rgb>("{}"), Err(Error::InvalidType));
+    }
+
+    #[test]
+    fn unit() {
+        use super::Error;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Ping;
+
+        assert_eq!(super::from_str("null"), Ok(()));
+        assert_eq!(super::from_str(" null "), Ok(Ping));
+        assert_eq!(super::from_str::<Ping>("{}"), Err(Error::InvalidType));
+    }
+
+    #[test]
+    fn unknown_fields() {
+        use super::{DeserializerConfig, Error};
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Led {
+            led: bool,
+        }
+
+        let json = br#"{ "extra": [1, {"b": "}\""}], "led": true, "note": "hi" }"#;
+
+        // ignored by default
+        assert_eq!(super::from_slice(json), Ok((Led { led: true }, json.len())));
+
+        let (led, unknown) = super::from_slice_with_unknown::<Led, 4>(json).unwrap();
+        assert_eq!(led, Led { led: true });
+        assert_eq!(
+            &unknown[..],
+            &[("extra", r#"[1, {"b": "}\""}]"#), ("note", r#""hi""#)][..]
+        );
+
+        // the options apply to the unknown fields as well
+        let config = DeserializerConfig {
+            allow_underscores: true,
+            ..DeserializerConfig::default()
+        };
+        let (_, unknown) = super::from_slice_with_unknown_with_options::<Led, 1>(
+            br#"{"led":true,"count":1_000}"#,
+            config,
+        )
+        .unwrap();
+        assert_eq!(&unknown[..], &[("count", "1_000")][..]);
+
+        // errors
+        assert_eq!(
+            super::from_slice_with_unknown::<Led, 1>(json).map(|(_, unknown)| unknown.len()),
+            Err(Error::TooManyUnknownFields)
+        );
+        assert_eq!(
+            super::from_slice_with_unknown::<Led, 4>(b"[true]").map(|(led, _)| led),
+            Err(Error::InvalidType)
+        );
+    }
+
+    #[test]
+    fn whitespace() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Led {
+            on: bool,
+            rgb: (u8, u8, u8),
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Strip<'a> {
+            leds: [Led; 2],
+            name: &'a str,
+        }
+
+        let compact = super::from_str::<Strip>(
+            r#"{"leds":[{"on":true,"rgb":[1,2,3]},{"on":false,"rgb":[0,0,0]}],"name":"a b"}"#,
+        ).unwrap();
+
+        let pretty = super::from_str(
+            r#"
+{
+  "leds" : [
+    { "on": true, "rgb": [ 1, 2, 3 ] },
+    {
+      "on":false ,
+      "rgb":  [0 ,0 ,0]
+    }
+  ] ,
+  "name": "a b"
+}
+"#,
+        );
+        assert_eq!(pretty, Ok(compact));
+
+        // tabs and CRLF line endings
+        assert_eq!(
+            super::from_str("\t[\r\n\t\t1,\t2,\r\n\t\t3\r\n\t]\r\n"),
+            Ok((1, 2, 3))
+        );
+        assert_eq!(super::from_str(" [ ] "), Ok([0u8; 0]));
+    }
+
     // See https://iot.mozilla.org/wot/#thing-resource
     #[test]
     #[ignore]
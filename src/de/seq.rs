@@ -1,22 +1,52 @@
-use serde::de;
+use serde::de::value::{U16Deserializer, U8Deserializer};
+use serde::de::{self, IntoDeserializer, Visitor};
 
+use base64::Decoder;
 use de::{Deserializer, Error, Result};
 
-pub(crate) struct SeqAccess<'a, 'b>
+pub(crate) struct SeqAccess<'a, 'b, 's>
 where
     'b: 'a,
+    's: 'a,
 {
     first: bool,
-    de: &'a mut Deserializer<'b>,
+    de: &'a mut Deserializer<'b, 's>,
+    /// Hand out missing elements (see `MissingElement`) once the array ends
+    tuple: bool,
+    /// The elements are bytes, see `Deserializer::deserialize_bytes`
+    bytes: bool,
 }
 
-impl<'a, 'b> SeqAccess<'a, 'b> {
-    pub fn new(de: &'a mut Deserializer<'b>) -> Self {
-        SeqAccess { de, first: true }
+impl<'a, 'b, 's> SeqAccess<'a, 'b, 's> {
+    pub fn new(de: &'a mut Deserializer<'b, 's>) -> Self {
+        SeqAccess {
+            de,
+            first: true,
+            tuple: false,
+            bytes: false,
+        }
+    }
+
+    pub fn tuple(de: &'a mut Deserializer<'b, 's>) -> Self {
+        SeqAccess {
+            de,
+            first: true,
+            tuple: true,
+            bytes: false,
+        }
+    }
+
+    pub fn bytes(de: &'a mut Deserializer<'b, 's>) -> Self {
+        SeqAccess {
+            de,
+            first: true,
+            tuple: false,
+            bytes: true,
+        }
     }
 }
 
-impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
+impl<'a, 'de, 's> de::SeqAccess<'de> for SeqAccess<'a, 'de, 's> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
@@ -27,6 +57,7 @@ impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
             .parse_whitespace()
             .ok_or(Error::EofWhileParsingList)?
         {
+            b']' if self.tuple => return seed.deserialize(MissingElement).map(Some),
             b']' => return Ok(None),
             b',' => {
                 self.de.eat_char();
@@ -46,8 +77,168 @@ impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
 
         if peek == b']' {
             Err(Error::TrailingComma)
+        } else if self.bytes {
+            let byte: u64 = de::Deserialize::deserialize(&mut *self.de)?;
+            if byte > 255 {
+                return Err(Error::ByteOutOfRange);
+            }
+
+            let de: U8Deserializer<Error> = (byte as u8).into_deserializer();
+            seed.deserialize(de).map(Some)
         } else {
             Ok(Some(seed.deserialize(&mut *self.de)?))
         }
     }
 }
+
+/// Elements of a packed array, see `DeserializerConfig::pack_arrays`
+pub(crate) struct PackedAccess<'a> {
+    decoder: Decoder<'a>,
+    /// Hand out missing elements (see `MissingElement`) once the array ends
+    tuple: bool,
+    /// Whether the elements are `u16`s rather than `u8`s
+    wide: bool,
+}
+
+impl<'a> PackedAccess<'a> {
+    /// `base64` is the content of the string minus the `u8:` or `u16:` header
+    pub fn new(base64: &'a [u8], wide: bool, tuple: bool) -> Self {
+        PackedAccess {
+            decoder: Decoder::new(base64),
+            tuple,
+            wide,
+        }
+    }
+
+    /// Checks that all the elements have been consumed
+    pub fn end(&mut self) -> Result<()> {
+        match self.next()? {
+            Some(_) => Err(Error::SeqTooLong),
+            None => Ok(()),
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<u16>> {
+        let byte = match self.decoder.next().map_err(|_| Error::InvalidBase64)? {
+            Some(byte) => byte,
+            None => return Ok(None),
+        };
+
+        if !self.wide {
+            return Ok(Some(byte as u16));
+        }
+
+        match self.decoder.next().map_err(|_| Error::InvalidBase64)? {
+            Some(low) => Ok(Some((byte as u16) << 8 | low as u16)),
+            // odd number of bytes
+            None => Err(Error::InvalidBase64),
+        }
+    }
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for PackedAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.next()? {
+            Some(v) if self.wide => {
+                let de: U16Deserializer<Error> = v.into_deserializer();
+                seed.deserialize(de).map(Some)
+            }
+            Some(v) => {
+                let de: U8Deserializer<Error> = (v as u8).into_deserializer();
+                seed.deserialize(de).map(Some)
+            }
+            None if self.tuple => seed.deserialize(MissingElement).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Bytes of a hex string, see `DeserializerConfig::bytes_hex`
+pub(crate) struct HexAccess<'a> {
+    hex: &'a [u8],
+}
+
+impl<'a> HexAccess<'a> {
+    pub fn new(hex: &'a [u8]) -> Self {
+        HexAccess { hex }
+    }
+
+    /// Checks that all the bytes have been consumed
+    pub fn end(&mut self) -> Result<()> {
+        match self.next()? {
+            Some(_) => Err(Error::SeqTooLong),
+            None => Ok(()),
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<u8>> {
+        match *self.hex {
+            [] => Ok(None),
+            [high, low, ref rest @ ..] => {
+                self.hex = rest;
+                Ok(Some(hex_digit(high)? << 4 | hex_digit(low)?))
+            }
+            // odd number of digits
+            _ => Err(Error::InvalidHex),
+        }
+    }
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for HexAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.next()? {
+            Some(v) => {
+                let de: U8Deserializer<Error> = v.into_deserializer();
+                seed.deserialize(de).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+fn hex_digit(c: u8) -> Result<u8> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(Error::InvalidHex),
+    }
+}
+
+/// Stands in for the elements a tuple expects past the end of the array
+///
+/// Only `Option`s can be missing; they deserialize as `None`.
+struct MissingElement;
+
+impl<'de> de::Deserializer<'de> for MissingElement {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(Error::MissingElement)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_none()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
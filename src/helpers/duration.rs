@@ -0,0 +1,147 @@
+use core::fmt::{self, Write};
+
+use heapless::String;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+// NOTE(INVALID_DURATION) `Deserialize` implementations can only report errors through the
+// `de::Error` constructors; `de::Error` maps this `Unexpected::Other` marker to
+// `Error::InvalidDuration`
+pub(crate) const INVALID_DURATION: &str = "ISO-8601 duration";
+
+/// A number of seconds that (de)serializes as an ISO-8601 duration string, e.g. `"PT1H30M"`
+///
+/// Only the hours, minutes and seconds components are supported. A zero duration is `"PT0S"`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Iso8601Duration(pub u32);
+
+impl Serialize for Iso8601Duration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let (hours, minutes, seconds) = (self.0 / 3600, self.0 / 60 % 60, self.0 % 60);
+
+        // NOTE(unwrap) "PT1193046H28M15S" is the longest possible output
+        let mut s: String<16> = String::new();
+        write!(s, "PT").unwrap();
+        if hours != 0 {
+            write!(s, "{}H", hours).unwrap();
+        }
+        if minutes != 0 {
+            write!(s, "{}M", minutes).unwrap();
+        }
+        if seconds != 0 || self.0 == 0 {
+            write!(s, "{}S", seconds).unwrap();
+        }
+
+        serializer.serialize_str(&s)
+    }
+}
+
+impl<'de> Deserialize<'de> for Iso8601Duration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct DurationVisitor;
+
+        impl<'de> Visitor<'de> for DurationVisitor {
+            type Value = Iso8601Duration;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an ISO-8601 duration like \"PT1H30M\"")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                parse(v).map(Iso8601Duration).ok_or_else(|| {
+                    E::invalid_value(de::Unexpected::Other(INVALID_DURATION), &self)
+                })
+            }
+        }
+
+        deserializer.deserialize_str(DurationVisitor)
+    }
+}
+
+/// Parses `PT[<n>H][<n>M][<n>S]` into a number of seconds
+fn parse(s: &str) -> Option<u32> {
+    if !s.starts_with("PT") || s.len() == 2 {
+        return None;
+    }
+
+    // each unit may appear at most once and in this order
+    let mut units: &[(u8, u32)] = &[(b'H', 3600), (b'M', 60), (b'S', 1)];
+    let mut number = None;
+    let mut seconds: u32 = 0;
+    for c in s[2..].bytes() {
+        match c {
+            b'0'..=b'9' => {
+                number = Some(
+                    number
+                        .unwrap_or(0u32)
+                        .checked_mul(10)?
+                        .checked_add((c - b'0') as u32)?,
+                );
+            }
+            _ => {
+                let n = number.take()?;
+                let i = units.iter().position(|&(unit, _)| unit == c)?;
+                seconds = seconds.checked_add(n.checked_mul(units[i].1)?)?;
+                units = &units[i + 1..];
+            }
+        }
+    }
+
+    // a number must be followed by its unit
+    if number.is_some() {
+        return None;
+    }
+
+    Some(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Iso8601Duration;
+    use de::{self, Error};
+    use ser;
+
+    #[test]
+    fn invalid() {
+        for json in &[
+            r#""""#,
+            r#""PT""#,
+            r#""P1D""#,
+            r#""PT1""#,
+            r#""PTM""#,
+            r#""PT1S1M""#,
+            r#""PT1H1H""#,
+            r#""PT4294967296S""#,
+        ] {
+            assert_eq!(
+                de::from_str::<Iso8601Duration>(json),
+                Err(Error::InvalidDuration)
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for &(seconds, expected) in &[
+            (0, r#""PT0S""#),
+            (45, r#""PT45S""#),
+            (90 * 60, r#""PT1H30M""#),
+            (3601, r#""PT1H1S""#),
+            (86400, r#""PT24H""#),
+            (u32::MAX, r#""PT1193046H28M15S""#),
+        ] {
+            let json = ser::to_string::<32, _>(&Iso8601Duration(seconds)).unwrap();
+            assert_eq!(&*json, expected);
+            assert_eq!(de::from_str(&json), Ok(Iso8601Duration(seconds)));
+        }
+    }
+}
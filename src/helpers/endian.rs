@@ -0,0 +1,89 @@
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+/// A `u32` that (de)serializes as an array of its bytes in little endian order
+///
+/// E.g. `LeBytes(0x01020304)` is `[4,3,2,1]`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LeBytes(pub u32);
+
+/// A `u32` that (de)serializes as an array of its bytes in big endian order
+///
+/// E.g. `BeBytes(0x01020304)` is `[1,2,3,4]`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BeBytes(pub u32);
+
+impl Serialize for LeBytes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        to_be_bytes(self.0.swap_bytes()).serialize(serializer)
+    }
+}
+
+impl Serialize for BeBytes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        to_be_bytes(self.0).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LeBytes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = <[u8; 4]>::deserialize(deserializer)?;
+        Ok(LeBytes(from_be_bytes(bytes).swap_bytes()))
+    }
+}
+
+impl<'de> Deserialize<'de> for BeBytes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = <[u8; 4]>::deserialize(deserializer)?;
+        Ok(BeBytes(from_be_bytes(bytes)))
+    }
+}
+
+fn from_be_bytes(bytes: [u8; 4]) -> u32 {
+    bytes.iter().fold(0, |n, byte| n << 8 | *byte as u32)
+}
+
+fn to_be_bytes(n: u32) -> [u8; 4] {
+    [(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, n as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BeBytes, LeBytes};
+    use de;
+    use ser;
+
+    #[test]
+    fn big_endian() {
+        let json = ser::to_string::<32, _>(&BeBytes(0x01020304)).unwrap();
+        assert_eq!(&*json, "[1,2,3,4]");
+        assert_eq!(de::from_str(&json), Ok(BeBytes(0x01020304)));
+
+        let json = ser::to_string::<32, _>(&BeBytes(0xdeadbeef)).unwrap();
+        assert_eq!(&*json, "[222,173,190,239]");
+        assert_eq!(de::from_str(&json), Ok(BeBytes(0xdeadbeef)));
+    }
+
+    #[test]
+    fn little_endian() {
+        let json = ser::to_string::<32, _>(&LeBytes(0x01020304)).unwrap();
+        assert_eq!(&*json, "[4,3,2,1]");
+        assert_eq!(de::from_str(&json), Ok(LeBytes(0x01020304)));
+
+        let json = ser::to_string::<32, _>(&LeBytes(0xdeadbeef)).unwrap();
+        assert_eq!(&*json, "[239,190,173,222]");
+        assert_eq!(de::from_str(&json), Ok(LeBytes(0xdeadbeef)));
+    }
+}
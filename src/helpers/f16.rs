@@ -0,0 +1,79 @@
+use half::f16;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+/// An `f32` that is stored with IEEE 754 half precision, e.g. a raw DSP sample
+///
+/// The value is rounded to the nearest half precision float both when serializing and when
+/// deserializing, so only about 3 significant decimal digits survive; e.g. `0.1` becomes
+/// `0.099975586`. Deserializing a number beyond the half precision range (+-65504) fails with
+/// `de::Error::InvalidNumber`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Half(pub f32);
+
+impl Half {
+    /// Returns the value as a half precision float
+    pub fn to_f16(self) -> f16 {
+        f16::from_f32(self.0)
+    }
+}
+
+impl Serialize for Half {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_f32(self.to_f16().to_f32())
+    }
+}
+
+impl<'de> Deserialize<'de> for Half {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let v = f32::deserialize(deserializer)?;
+        let rounded = f16::from_f32(v).to_f32();
+
+        if v.is_finite() && !rounded.is_finite() {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Float(v as f64),
+                &"a half precision float",
+            ));
+        }
+
+        Ok(Half(rounded))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Half;
+    use de::{self, Error};
+    use ser;
+
+    #[test]
+    fn out_of_range() {
+        assert_eq!(de::from_str::<Half>("70000"), Err(Error::InvalidNumber));
+        assert_eq!(de::from_str::<Half>("-1e10"), Err(Error::InvalidNumber));
+    }
+
+    #[test]
+    fn round_trip() {
+        for &(v, expected) in &[
+            (0., "0"),
+            (0.5, "0.5"),
+            (-2.25, "-2.25"),
+            (1000., "1000"),
+            (65504., "65504"),
+        ] {
+            let json = ser::to_string::<32, _>(&Half(v)).unwrap();
+            assert_eq!(&*json, expected);
+            assert_eq!(de::from_str(&json), Ok(Half(v)));
+        }
+
+        // rounded to the nearest half precision float
+        assert_eq!(de::from_str("0.1"), Ok(Half(1638. / 16384.)));
+        assert_eq!(de::from_str("1000.3"), Ok(Half(1000.5)));
+    }
+}
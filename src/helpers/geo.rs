@@ -0,0 +1,151 @@
+use core::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+
+/// A geographic position that serializes as a GeoJSON position, `[lon,lat]` or `[lon,lat,alt]`
+///
+/// Note that GeoJSON puts the longitude first. The fields are not range checked when serializing;
+/// use `GeoPoint::new` to reject longitudes outside `-180..180` and latitudes outside `-90..90`.
+/// Deserializing an out of range point fails with `de::Error::InvalidNumber`, and an array that
+/// doesn't have 2 or 3 elements fails as well.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeoPoint {
+    /// Longitude in degrees
+    pub lon: f64,
+    /// Latitude in degrees
+    pub lat: f64,
+    /// Altitude in meters, serialized as a third element if present
+    pub alt: Option<f64>,
+}
+
+impl GeoPoint {
+    /// Creates a point without altitude, or returns `None` if `lon` or `lat` are out of range
+    pub fn new(lon: f64, lat: f64) -> Option<Self> {
+        if valid_lon(lon) && valid_lat(lat) {
+            Some(GeoPoint {
+                lon,
+                lat,
+                alt: None,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl Serialize for GeoPoint {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let len = if self.alt.is_some() { 3 } else { 2 };
+
+        let mut tuple = serializer.serialize_tuple(len)?;
+        tuple.serialize_element(&self.lon)?;
+        tuple.serialize_element(&self.lat)?;
+        if let Some(alt) = self.alt {
+            tuple.serialize_element(&alt)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for GeoPoint {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(3, GeoPointVisitor)
+    }
+}
+
+struct GeoPointVisitor;
+
+impl<'de> Visitor<'de> for GeoPointVisitor {
+    type Value = GeoPoint;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of 2 or 3 numbers")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<GeoPoint, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let lon: f64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        if !valid_lon(lon) {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Float(lon),
+                &"a longitude between -180 and 180",
+            ));
+        }
+
+        let lat: f64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        if !valid_lat(lat) {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Float(lat),
+                &"a latitude between -90 and 90",
+            ));
+        }
+
+        let alt = seq.next_element::<Option<f64>>()?.and_then(|alt| alt);
+
+        Ok(GeoPoint { lon, lat, alt })
+    }
+}
+
+fn valid_lat(lat: f64) -> bool {
+    (-90.0..=90.0).contains(&lat)
+}
+
+fn valid_lon(lon: f64) -> bool {
+    (-180.0..=180.0).contains(&lon)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeoPoint;
+    use de::{self, Error};
+    use ser;
+
+    #[test]
+    fn invalid() {
+        assert_eq!(de::from_str::<GeoPoint>("[181,0]"), Err(Error::InvalidNumber));
+        assert_eq!(de::from_str::<GeoPoint>("[0,-91]"), Err(Error::InvalidNumber));
+        assert_eq!(de::from_str::<GeoPoint>("[0]"), Err(Error::MissingElement));
+        assert_eq!(de::from_str::<GeoPoint>("[]"), Err(Error::MissingElement));
+        assert!(de::from_str::<GeoPoint>("[0,0,0,0]").is_err());
+    }
+
+    #[test]
+    fn new() {
+        assert!(GeoPoint::new(-180., 90.).is_some());
+        assert!(GeoPoint::new(180.5, 0.).is_none());
+        assert!(GeoPoint::new(0., -90.5).is_none());
+        assert!(GeoPoint::new(f64::NAN, 0.).is_none());
+    }
+
+    #[test]
+    fn point_2d() {
+        let point = GeoPoint::new(2.2945, 48.8584).unwrap();
+        let json = ser::to_string::<32, _>(&point).unwrap();
+        assert_eq!(&*json, "[2.2945,48.8584]");
+        assert_eq!(de::from_str(&json), Ok(point));
+    }
+
+    #[test]
+    fn point_3d() {
+        let point = GeoPoint {
+            alt: Some(330.),
+            ..GeoPoint::new(2.2945, 48.8584).unwrap()
+        };
+        let json = ser::to_string::<32, _>(&point).unwrap();
+        assert_eq!(&*json, "[2.2945,48.8584,330]");
+        assert_eq!(de::from_str(&json), Ok(point));
+    }
+}
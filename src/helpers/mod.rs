@@ -0,0 +1,15 @@
+//! Helper types that (de)serialize into commonly used JSON representations
+
+pub use self::duration::Iso8601Duration;
+pub use self::endian::{BeBytes, LeBytes};
+#[cfg(feature = "half")]
+pub use self::f16::Half;
+pub use self::geo::GeoPoint;
+
+pub(crate) use self::duration::INVALID_DURATION;
+
+mod duration;
+mod endian;
+#[cfg(feature = "half")]
+mod f16;
+mod geo;
@@ -15,26 +15,41 @@
 //! - Supports deserialization of:
 //!   - `bool`
 //!   - Integers
+//!   - Floats
+//!   - `char`
 //!   - `str` (This is a zero copy operation.) (\*)
+//!   - Byte strings, from an array of numbers, a string or, optionally, a base64 or hex string
+//!   - `heapless::String` and `heapless::Vec`
 //!   - `Option`
+//!   - Unit, `()`, and unit structs
 //!   - Arrays
-//!   - Tuples
+//!   - Tuples, tuple structs and newtype structs
 //!   - Structs
-//!   - C like enums
-//! - Supports serialization (compact format only) of:
+//!   - Enums: C like, externally tagged and, with `DeserializerConfig::enum_tag`, internally
+//!     tagged
+//!   - Maps
+//! - Supports serialization, compact or pretty printed (see `ser::to_string_pretty`), of:
 //!   - `bool`
 //!   - Integers
-//!   - `str` (\*\*)
+//!   - Floats
+//!   - `char`
+//!   - `str`
+//!   - Byte strings, as an array of numbers or as a base64, data URI or hex string
 //!   - `Option`
+//!   - Unit, `()`, and unit structs
 //!   - Arrays
-//!   - Tuples
+//!   - Tuples, tuple structs and newtype structs
 //!   - Structs
-//!   - C like enums
+//!   - Enums, all kinds of variants
+//!   - Maps
+//!   - References, `RefCell` and, with the `std` feature, `Mutex` (serialized as the value they
+//!     wrap; `RefCell` and `Mutex` are borrowed / locked for the duration of the serialization)
+//! - Serializes into a `heapless::String` or `heapless::Vec`, a plain `&mut [u8]` (see
+//!   `ser::to_slice`) or a `core::fmt::Write` sink (see `ser::to_writer`)
 //!
-//! (\*) Deserialization of strings ignores escaped sequences. Escaped sequences might be supported
-//! in the future using a different Serializer as this operation is not zero copy.
-//!
-//! (\*\*) Serialization of strings doesn't escape stuff. This simply has not been implemented yet.
+//! (\*) Strings that contain escape sequences can't be borrowed from the input and are rejected
+//! with `de::Error::EscapeInBorrowedStr`; `de::from_slice_escaped` unescapes them into a scratch
+//! buffer provided by the caller instead.
 //!
 //! # Planned features
 //!
@@ -49,16 +64,28 @@
 //!     type
 #![deny(missing_docs)]
 #![deny(warnings)]
-#![feature(unsize)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
 #[cfg(feature = "std")]
 extern crate core;
+#[cfg(feature = "defmt")]
+extern crate defmt;
+#[cfg(feature = "embedded-hal")]
+extern crate embedded_hal;
+#[cfg(feature = "half")]
+extern crate half;
 extern crate heapless;
+#[cfg(feature = "ryu")]
+extern crate ryu;
+#[macro_use]
 extern crate serde;
 #[cfg(test)]
 #[macro_use]
 extern crate serde_derive;
+#[cfg(test)]
+extern crate serde_json;
 
+mod base64;
 pub mod de;
+pub mod helpers;
 pub mod ser;
@@ -0,0 +1,413 @@
+use core::{cmp, fmt, str};
+
+#[cfg(feature = "embedded-hal")]
+use embedded_hal::blocking::serial;
+use heapless::Vec;
+
+use ser::{Error, Result};
+
+/// Output of the `Emitter`
+pub trait Buffer {
+    /// The bytes written so far
+    fn as_slice(&self) -> &[u8];
+
+    /// The bytes written so far, for the options that reorder the output in place
+    fn as_mut_slice(&mut self) -> &mut [u8];
+
+    /// Maximum number of bytes the buffer can hold
+    fn capacity(&self) -> usize;
+
+    /// Appends `bytes`, failing with `Error::BufferFull` if they don't fit
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<()>;
+
+    /// Appends `byte`, failing with `Error::BufferFull` if it doesn't fit
+    fn push(&mut self, byte: u8) -> Result<()>;
+
+    /// Discards everything written after the first `len` bytes
+    fn truncate(&mut self, len: usize);
+
+    /// Number of bytes written so far
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    /// The error to report when `additional` more bytes don't fit
+    fn full(&self, additional: usize) -> Error {
+        Error::BufferFull {
+            capacity: self.capacity(),
+            needed: self.len() + additional,
+        }
+    }
+}
+
+impl<const N: usize> Buffer for Vec<u8, N> {
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn capacity(&self) -> usize {
+        Vec::capacity(self)
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<()> {
+        Vec::extend_from_slice(self, bytes).map_err(|_| self.full(bytes.len()))
+    }
+
+    fn push(&mut self, byte: u8) -> Result<()> {
+        Vec::push(self, byte).map_err(|_| self.full(1))
+    }
+
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len)
+    }
+}
+
+impl<B> Buffer for &mut B
+where
+    B: Buffer,
+{
+    fn as_slice(&self) -> &[u8] {
+        B::as_slice(self)
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        B::as_mut_slice(self)
+    }
+
+    fn capacity(&self) -> usize {
+        B::capacity(self)
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<()> {
+        B::extend_from_slice(self, bytes)
+    }
+
+    fn push(&mut self, byte: u8) -> Result<()> {
+        B::push(self, byte)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        B::truncate(self, len)
+    }
+
+    fn len(&self) -> usize {
+        B::len(self)
+    }
+
+    fn full(&self, additional: usize) -> Error {
+        B::full(self, additional)
+    }
+}
+
+/// Caller provided slice plus the number of bytes written into it, see `to_slice`
+pub struct Cursor<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(buf: &'a mut [u8]) -> Self {
+        Cursor { buf, len: 0 }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'a> Buffer for Cursor<'a> {
+    fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.buf[..self.len]
+    }
+
+    fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.len + bytes.len();
+        if end > self.buf.len() {
+            return Err(self.full(bytes.len()));
+        }
+
+        self.buf[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    fn push(&mut self, byte: u8) -> Result<()> {
+        self.extend_from_slice(&[byte])
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.len = cmp::min(self.len, len);
+    }
+}
+
+/// Sink that only counts the bytes written into it, see `to_string_exact`
+///
+/// NOTE nothing is stored so `as_slice` and `as_mut_slice` are unimplemented, see `Writer`
+pub struct Counter {
+    len: usize,
+}
+
+impl Counter {
+    pub(crate) fn new() -> Self {
+        Counter { len: 0 }
+    }
+}
+
+impl Buffer for Counter {
+    fn as_slice(&self) -> &[u8] {
+        unreachable!()
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unreachable!()
+    }
+
+    fn capacity(&self) -> usize {
+        usize::MAX
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<()> {
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    fn push(&mut self, _byte: u8) -> Result<()> {
+        self.len += 1;
+        Ok(())
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.len = cmp::min(self.len, len);
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Caller provided segments that are filled one after the other, see `to_slices`
+///
+/// NOTE the output is not contiguous so `as_slice` and `as_mut_slice` are unimplemented; they are
+/// only called for the options that rewrite the output (`crc_field`, `pack_arrays` and
+/// `skip_empty_strings`) and by `to_vec_canonical`, which `to_slices` doesn't use
+pub struct Segments<'a, 'b>
+where
+    'b: 'a,
+{
+    segments: &'a mut [&'b mut [u8]],
+    len: usize,
+}
+
+impl<'a, 'b> Segments<'a, 'b> {
+    pub(crate) fn new(segments: &'a mut [&'b mut [u8]]) -> Self {
+        Segments { segments, len: 0 }
+    }
+
+    /// Number of bytes written into each segment
+    pub(crate) fn counts<const S: usize>(&self) -> [usize; S] {
+        let mut counts = [0; S];
+        let mut rest = self.len;
+        for (count, segment) in counts.iter_mut().zip(self.segments.iter()) {
+            *count = cmp::min(rest, segment.len());
+            rest -= *count;
+        }
+        counts
+    }
+}
+
+impl<'a, 'b> Buffer for Segments<'a, 'b> {
+    fn as_slice(&self) -> &[u8] {
+        unreachable!()
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unreachable!()
+    }
+
+    fn capacity(&self) -> usize {
+        self.segments.iter().map(|segment| segment.len()).sum()
+    }
+
+    fn extend_from_slice(&mut self, mut bytes: &[u8]) -> Result<()> {
+        if self.len + bytes.len() > self.capacity() {
+            return Err(self.full(bytes.len()));
+        }
+
+        // offset of `self.len` in the segment it falls into
+        let mut offset = self.len;
+        for segment in self.segments.iter_mut() {
+            if bytes.is_empty() {
+                break;
+            }
+            if offset >= segment.len() {
+                offset -= segment.len();
+                continue;
+            }
+
+            let n = cmp::min(segment.len() - offset, bytes.len());
+            segment[offset..offset + n].copy_from_slice(&bytes[..n]);
+            bytes = &bytes[n..];
+            self.len += n;
+            offset = 0;
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, byte: u8) -> Result<()> {
+        self.extend_from_slice(&[byte])
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.len = cmp::min(self.len, len);
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Adapter that sends the output to a serial port as it's produced, see `to_serial`
+///
+/// NOTE like `Writer`, `as_slice`, `as_mut_slice` and `truncate` are unimplemented
+#[cfg(feature = "embedded-hal")]
+pub struct Serial<'a, S>
+where
+    S: serial::Write<u8> + 'a,
+{
+    len: usize,
+    serial: &'a mut S,
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<'a, S> Serial<'a, S>
+where
+    S: serial::Write<u8>,
+{
+    pub(crate) fn new(serial: &'a mut S) -> Self {
+        Serial { len: 0, serial }
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<'a, S> Buffer for Serial<'a, S>
+where
+    S: serial::Write<u8>,
+{
+    fn as_slice(&self) -> &[u8] {
+        unreachable!()
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unreachable!()
+    }
+
+    fn capacity(&self) -> usize {
+        usize::MAX
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<()> {
+        self.serial
+            .bwrite_all(bytes)
+            .map_err(|_| Error::WriteFailed)?;
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    fn push(&mut self, byte: u8) -> Result<()> {
+        self.extend_from_slice(&[byte])
+    }
+
+    fn truncate(&mut self, _len: usize) {
+        unreachable!()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn full(&self, _additional: usize) -> Error {
+        Error::WriteFailed
+    }
+}
+
+/// Adapter that forwards the output to a `fmt::Write` sink, see `to_writer`
+///
+/// NOTE the sink can't be read back nor rewound so `as_slice`, `as_mut_slice` and `truncate` are
+/// unimplemented; they are only called for the options that rewrite the output (`crc_field`,
+/// `pack_arrays` and `skip_empty_strings`) and by `to_vec_canonical`, which `to_writer` doesn't
+/// use
+pub struct Writer<'a, W>
+where
+    W: fmt::Write + 'a,
+{
+    len: usize,
+    writer: &'a mut W,
+}
+
+impl<'a, W> Writer<'a, W>
+where
+    W: fmt::Write,
+{
+    pub(crate) fn new(writer: &'a mut W) -> Self {
+        Writer { len: 0, writer }
+    }
+}
+
+impl<'a, W> Buffer for Writer<'a, W>
+where
+    W: fmt::Write,
+{
+    fn as_slice(&self) -> &[u8] {
+        unreachable!()
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unreachable!()
+    }
+
+    fn capacity(&self) -> usize {
+        usize::MAX
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<()> {
+        // NOTE(unsafe) the serializer only ever writes whole UTF-8 sequences: escaping splits
+        // strings at ASCII characters and everything else it writes is ASCII
+        let s = unsafe { str::from_utf8_unchecked(bytes) };
+        self.writer.write_str(s).map_err(|_| Error::WriteFailed)?;
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    fn push(&mut self, byte: u8) -> Result<()> {
+        // `byte` is always ASCII, see `extend_from_slice`
+        self.writer
+            .write_char(byte as char)
+            .map_err(|_| Error::WriteFailed)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn truncate(&mut self, _len: usize) {
+        unreachable!()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn full(&self, _additional: usize) -> Error {
+        Error::WriteFailed
+    }
+}
@@ -0,0 +1,143 @@
+//! Functions that format values straight into the serializer output
+//!
+//! They serialize a number of milliseconds since the Unix epoch as an RFC-3339 string. Use them
+//! with `#[serde(serialize_with = "...")]` on `u64` fields, e.g.
+//! `#[serde(serialize_with = "serde_json_core::ser::fmt::rfc3339")]` turns
+//! `1_000_000_000_000` into `"2001-09-09T01:46:40.000Z"`. The string is formatted straight into
+//! the serializer's output, through `collect_str`, without a buffer in between.
+//!
+//! The date-time is always in UTC. Timestamps from the year 10000 on are written with a longer
+//! year, which is outside RFC-3339.
+
+use core::fmt;
+
+use serde::ser::Serializer;
+
+const MILLIS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// Serializes `millis` with millisecond precision, e.g. `"2001-09-09T01:46:40.000Z"`
+pub fn rfc3339<S>(millis: &u64, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&DateTime {
+        millis: *millis,
+        fraction: true,
+    })
+}
+
+/// Serializes `millis` truncated to whole seconds, e.g. `"2001-09-09T01:46:40Z"`
+pub fn rfc3339_seconds<S>(millis: &u64, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&DateTime {
+        millis: *millis,
+        fraction: false,
+    })
+}
+
+struct DateTime {
+    millis: u64,
+    /// Whether to write the milliseconds
+    fraction: bool,
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (year, month, day) = civil_from_days(self.millis / MILLIS_PER_DAY);
+        let time = self.millis % MILLIS_PER_DAY;
+        let (hours, minutes, seconds) = (time / 3_600_000, time / 60_000 % 60, time / 1000 % 60);
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year, month, day, hours, minutes, seconds
+        )?;
+        if self.fraction {
+            write!(f, ".{:03}", time % 1000)?;
+        }
+        f.write_str("Z")
+    }
+}
+
+/// Converts a number of days since 1970-01-01 into a (year, month, day) date
+///
+/// This is Howard Hinnant's `civil_from_days` algorithm, restricted to dates from the epoch on. It
+/// counts in 400 year eras of the proleptic Gregorian calendar, with years starting on March 1st
+/// so the leap day is the last day of the year.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // days since 0000-03-01
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // March is 0
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+
+    let year = era * 400 + year_of_era;
+    if month < 10 {
+        (year, month + 3, day)
+    } else {
+        (year + 1, month - 9, day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ser;
+
+    #[derive(Serialize)]
+    struct Reading {
+        #[serde(serialize_with = "super::rfc3339")]
+        at: u64,
+    }
+
+    #[derive(Serialize)]
+    struct Event {
+        #[serde(serialize_with = "super::rfc3339_seconds")]
+        at: u64,
+    }
+
+    #[test]
+    fn millis() {
+        let to_string = |at| ser::to_string::<64, _>(&Reading { at }).unwrap();
+
+        assert_eq!(&*to_string(0), r#"{"at":"1970-01-01T00:00:00.000Z"}"#);
+        assert_eq!(
+            &*to_string(1_000_000_000_000),
+            r#"{"at":"2001-09-09T01:46:40.000Z"}"#
+        );
+        assert_eq!(
+            &*to_string(1_700_000_000_123),
+            r#"{"at":"2023-11-14T22:13:20.123Z"}"#
+        );
+        // leap day
+        assert_eq!(
+            &*to_string(951_782_400_000),
+            r#"{"at":"2000-02-29T00:00:00.000Z"}"#
+        );
+        assert_eq!(
+            &*to_string(253_402_300_799_999),
+            r#"{"at":"9999-12-31T23:59:59.999Z"}"#
+        );
+    }
+
+    #[test]
+    fn seconds() {
+        let to_string = |at| ser::to_string::<64, _>(&Event { at }).unwrap();
+
+        assert_eq!(&*to_string(0), r#"{"at":"1970-01-01T00:00:00Z"}"#);
+        assert_eq!(
+            &*to_string(1_700_000_000_999),
+            r#"{"at":"2023-11-14T22:13:20Z"}"#
+        );
+        assert_eq!(
+            &*to_string(951_868_799_000),
+            r#"{"at":"2000-02-29T23:59:59Z"}"#
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use core::fmt;
+
+use serde::ser;
+
+use ser::{Buffer, Emitter, Error, Result, Unreachable};
+
+/// Serializes maps as JSON objects
+///
+/// Entries are written in the order serde passes them to `serialize_key` / `serialize_value`, which
+/// is the iteration order of the map: no sorting or deduplication is done here. E.g. a
+/// `heapless::LinearMap` is written in insertion order and a `BTreeMap` in key order.
+///
+/// Structs with `#[serde(flatten)]` fields are also serialized as maps by serde: the fields of the
+/// outer and of the flattened structs all come through here as entries of a single object. The
+/// options that only apply to structs, like `SerializerConfig::crc_field`, don't apply to them.
+/// Deriving `Serialize` for such structs requires serde's `std` or `alloc` feature.
+///
+/// The length hint of `serialize_map` is not used to place the commas up front: serde lets it be
+/// wrong (e.g. `collect_map` passes the `size_hint` of the iterator), and a single `first` flag is
+/// all it would save. Maps with and without a hint produce the same output.
+pub struct SerializeMap<'a, B>
+where
+    B: Buffer + 'a,
+{
+    de: &'a mut Emitter<B>,
+    first: bool,
+    /// The map was serialized as the truncation placeholder; its entries are discarded
+    skip: bool,
+    /// Buffer position of the first entry
+    start: usize,
+}
+
+impl<'a, B> SerializeMap<'a, B>
+where
+    B: Buffer,
+{
+    pub(crate) fn new(de: &'a mut Emitter<B>) -> Self {
+        SerializeMap {
+            start: de.buf.len(),
+            de,
+            first: true,
+            skip: false,
+        }
+    }
+
+    pub(crate) fn skip(de: &'a mut Emitter<B>) -> Self {
+        SerializeMap {
+            de,
+            first: true,
+            skip: true,
+            start: 0,
+        }
+    }
+}
+
+impl<'a, B> ser::SerializeMap for SerializeMap<'a, B>
+where
+    B: Buffer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        if self.skip {
+            return Ok(());
+        }
+
+        if self.first {
+            self.de.newline()?;
+        } else {
+            self.de.comma()?;
+        }
+        self.first = false;
+
+        key.serialize(MapKeySerializer { de: &mut *self.de })?;
+        self.de.colon()
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        if self.skip {
+            return Ok(());
+        }
+
+        value.serialize(&mut *self.de)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        if !self.skip {
+            if self.de.sort_keys && !self.first {
+                // a trailing comma makes all the entries look alike, as `"key":value,`
+                self.de.buf.push(b',')?;
+                sort_entries(&mut self.de.buf.as_mut_slice()[self.start..]);
+                let len = self.de.buf.len();
+                self.de.buf.truncate(len - 1);
+            }
+
+            self.de.depth -= 1;
+            if !self.first {
+                self.de.newline()?;
+            }
+            self.de.buf.push(b'}')?;
+        }
+        Ok(())
+    }
+}
+
+/// Sorts `entries`, a sequence of compact `"key":value,` entries, by key
+///
+/// This is an insertion sort that moves the entries in place, so it takes no memory but is
+/// quadratic in the number of entries. It's stable: entries with the same key keep their order.
+fn sort_entries(entries: &mut [u8]) {
+    let mut sorted = entry_len(entries);
+    while sorted < entries.len() {
+        let end = sorted + entry_len(&entries[sorted..]);
+
+        let mut at = 0;
+        while at < sorted && key(&entries[at..]) <= key(&entries[sorted..]) {
+            at += entry_len(&entries[at..]);
+        }
+        entries[at..end].rotate_right(end - sorted);
+
+        sorted = end;
+    }
+}
+
+/// Length of the first entry of `entries`, trailing comma included
+fn entry_len(entries: &[u8]) -> usize {
+    let mut depth = 0;
+    let mut string = false;
+    let mut escape = false;
+    for (i, byte) in entries.iter().enumerate() {
+        if string {
+            match *byte {
+                _ if escape => escape = false,
+                b'\\' => escape = true,
+                b'"' => string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match *byte {
+            b'"' => string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => depth -= 1,
+            b',' if depth == 0 => return i + 1,
+            _ => {}
+        }
+    }
+    entries.len()
+}
+
+/// The serialized key, without the quotes, of the first entry of `entries`
+fn key(entries: &[u8]) -> &[u8] {
+    let mut escape = false;
+    for (i, byte) in entries.iter().enumerate().skip(1) {
+        match *byte {
+            _ if escape => escape = false,
+            b'\\' => escape = true,
+            b'"' => return &entries[1..i],
+            _ => {}
+        }
+    }
+    entries
+}
+
+/// Serializes map keys, which must be JSON strings
+///
+/// Integer keys are serialized as strings, e.g. `{"1":true}`, if
+/// `SerializerConfig::quote_integer_keys` is set; other non-string keys are rejected with
+/// `Error::KeyMustBeAString`.
+struct MapKeySerializer<'a, B>
+where
+    B: Buffer + 'a,
+{
+    de: &'a mut Emitter<B>,
+}
+
+macro_rules! serialize_quoted {
+    ($self:ident, $serialize:ident, $v:expr) => {{
+        if !$self.de.config.quote_integer_keys {
+            return Err(Error::KeyMustBeAString);
+        }
+
+        $self.de.buf.push(b'"')?;
+        ser::Serializer::$serialize(&mut *$self.de, $v)?;
+        $self.de.buf.push(b'"')?;
+        Ok(())
+    }};
+}
+
+impl<'a, B> ser::Serializer for MapKeySerializer<'a, B>
+where
+    B: Buffer,
+{
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Unreachable;
+    type SerializeTuple = Unreachable;
+    type SerializeTupleStruct = Unreachable;
+    type SerializeTupleVariant = Unreachable;
+    type SerializeMap = Unreachable;
+    type SerializeStruct = Unreachable;
+    type SerializeStructVariant = Unreachable;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
+        serialize_quoted!(self, serialize_i8, v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok> {
+        serialize_quoted!(self, serialize_i16, v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
+        serialize_quoted!(self, serialize_i32, v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
+        serialize_quoted!(self, serialize_i64, v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
+        serialize_quoted!(self, serialize_u8, v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
+        serialize_quoted!(self, serialize_u16, v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
+        serialize_quoted!(self, serialize_u32, v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
+        serialize_quoted!(self, serialize_u64, v)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        ser::Serializer::serialize_char(self.de, v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok> {
+        ser::Serializer::serialize_str(self.de, v)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        ser::Serializer::serialize_str(self.de, variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn collect_str<T>(self, value: &T) -> Result<Self::Ok>
+    where
+        T: fmt::Display + ?Sized,
+    {
+        ser::Serializer::collect_str(self.de, value)
+    }
+}
@@ -1,15 +1,27 @@
 //! Serialize a Rust data structure into JSON data
 
-use core::marker::Unsize;
-use core::{fmt, mem};
+use core::fmt::Write;
+use core::str;
 
 use serde::ser;
 
-use heapless::{BufferFullError, String, Vec};
+use heapless::{String, Vec};
 
-use self::seq::SerializeSeq;
+use base64::Encoder;
+
+pub use self::seq::SeqWriter;
+
+#[cfg(feature = "embedded-hal")]
+use self::buffer::Serial;
+use self::buffer::{Buffer, Counter, Cursor, Segments, Writer};
+use self::map::SerializeMap;
+use self::seq::{Packed, SerializeSeq};
 use self::struct_::SerializeStruct;
 
+mod buffer;
+#[cfg(feature = "rfc3339")]
+pub mod fmt;
+mod map;
 mod seq;
 mod struct_;
 
@@ -18,45 +30,629 @@ pub type Result<T> = ::core::result::Result<T, Error>;
 
 /// This type represents all possible errors that can occur when serializing JSON data
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Buffer is full
-    BufferFull,
-    #[doc(hidden)]
-    __Extensible,
+    BufferFull {
+        /// Size of the buffer
+        capacity: usize,
+        /// Lower bound of the size the buffer needs to have: the bytes written so far plus the
+        /// ones that didn't fit. Formatted output, like floats, counts as a single byte as its
+        /// length is not known up front.
+        needed: usize,
+    },
+    /// Error reported by a `Serialize` implementation with a custom message, which is dropped
+    ///
+    /// E.g. `serde` reports this for `#[serde(flatten)]` fields that are not structs or maps.
+    Custom,
+    /// Float is NaN or infinite, see `SerializerConfig::non_finite`
+    InvalidFloat,
+    /// `SerializerConfig::jsonp_callback` is not a valid JavaScript identifier
+    InvalidJsonpCallback,
+    /// Element of a packed array is not of the same type as the first one, see
+    /// `SerializerConfig::pack_arrays`
+    InvalidPackedElement,
+    /// Map key is not a string, see `SerializerConfig::quote_integer_keys`
+    KeyMustBeAString,
+    /// The `fmt::Write` sink returned an error, see `to_writer`
+    WriteFailed,
 }
 
-#[cfg(feature = "std")]
-impl ::std::error::Error for Error {
-    fn description(&self) -> &str {
-        ""
+impl ::core::error::Error for Error {}
+
+impl ::core::fmt::Display for Error {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        f.write_str(match *self {
+            Error::BufferFull { capacity, needed } => {
+                return write!(
+                    f,
+                    "buffer is full: at least {} bytes needed, capacity is {}",
+                    needed, capacity
+                )
+            }
+            Error::Custom => "custom error",
+            Error::InvalidFloat => "NaN or infinite float",
+            Error::InvalidJsonpCallback => "JSONP callback is not a valid JavaScript identifier",
+            Error::InvalidPackedElement => "packed array elements are not all of the same type",
+            Error::KeyMustBeAString => "map key must be a string",
+            Error::WriteFailed => "failed to write into the output sink",
+        })
     }
 }
 
-impl From<BufferFullError> for Error {
-    fn from(_: BufferFullError) -> Self {
-        Error::BufferFull
+#[cfg(feature = "defmt")]
+impl defmt::Format for Error {
+    fn format(&self, f: defmt::Formatter) {
+        match *self {
+            Error::BufferFull { capacity, needed } => defmt::write!(
+                f,
+                "buffer is full: at least {} bytes needed, capacity is {}",
+                needed,
+                capacity
+            ),
+            Error::Custom => defmt::write!(f, "custom error"),
+            Error::InvalidFloat => defmt::write!(f, "NaN or infinite float"),
+            Error::InvalidJsonpCallback => {
+                defmt::write!(f, "JSONP callback is not a valid JavaScript identifier")
+            }
+            Error::InvalidPackedElement => {
+                defmt::write!(f, "packed array elements are not all of the same type")
+            }
+            Error::KeyMustBeAString => defmt::write!(f, "map key must be a string"),
+            Error::WriteFailed => defmt::write!(f, "failed to write into the output sink"),
+        }
     }
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
-        unreachable!()
+/// Options that tweak the serializer output
+///
+/// The default configuration produces compact JSON
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SerializerConfig {
+    /// Tell `Serialize` implementations that the output is not meant to be human readable
+    ///
+    /// Types like `Ipv4Addr` then pick their compact representation, e.g. `[127,0,0,1]` instead of
+    /// `"127.0.0.1"`. Deserialize such data with `de::DeserializerConfig::binary` set.
+    pub binary: bool,
+
+    /// Representation of byte strings, i.e. types that call `serialize_bytes`
+    pub bytes_format: BytesFormat,
+
+    /// Append a `"key":crc` field at the end of the top level object
+    ///
+    /// The CRC-32 (IEEE 802.3, the one used by zlib and Ethernet) covers the object as serialized
+    /// up to that point: from its opening `{` up to the end of the last other field, excluding the
+    /// separator in front of the CRC field. E.g. it's computed over `{"id":7` in
+    /// `{"id":7,"crc":3218891522}`. Output written around the object, like the
+    /// `jsonp_callback`, is not covered. Only a top level struct gets this field. Use
+    /// `de::from_slice_with_crc` to verify it.
+    pub crc_field: Option<&'static str>,
+
+    /// Serialize `Some` of an empty sequence as `null`, the same as `None`
+    ///
+    /// This applies when the sequence is the direct content of the `Option` and reports its length
+    /// up front (e.g. slices, arrays and `Vec`), so `Some(&[][..])` becomes `null` while
+    /// `Some(&[1])` is still `[1]`. Empty sequences that are not wrapped in `Some`, or that are
+    /// nested deeper inside the `Option` (e.g. a field of a struct), are still serialized as `[]`.
+    pub empty_collection_as_null: bool,
+
+    /// Also escape DEL (0x7F) in strings, and pick the form of the escapes of control characters
+    ///
+    /// Control characters below 0x20 are always escaped, as JSON requires, using the short forms
+    /// like `\n` and `\t` where they exist. With this option DEL is escaped as `\u007F` as well,
+    /// and every control character is written as `\u00XX` if `use_short_escapes` is `false`, so
+    /// e.g. a tab becomes `\u0009`.
+    pub escape_all_control: Option<ControlEscapes>,
+
+    /// Notation used for floats
+    ///
+    /// Ignored for finite floats when `significant_digits` is set.
+    pub float_format: FloatFormat,
+
+    /// Pretty print the output, indenting each level of arrays and objects by this string
+    ///
+    /// Each element and member goes on its own line and a space follows each `:`, like
+    /// `serde_json`'s pretty printer; `separators` is ignored. Empty arrays and objects are still
+    /// serialized as `[]` and `{}`. See `to_string_pretty` and `PrettyConfig`.
+    pub indent: Option<&'static str>,
+
+    /// Add a `"key":version` field, e.g. `"_v":2`, at the start of the top level object
+    ///
+    /// Only a top level struct gets this field; nested structs, and structs inside a top level
+    /// array, are serialized as usual.
+    pub inject_version: Option<(&'static str, u32)>,
+
+    /// Wrap the output in a call to this JavaScript function, e.g. `cb({"a":1});`, for JSONP
+    ///
+    /// The name must be a plain JavaScript identifier (ASCII letters, digits, `_` and `$`, not
+    /// starting with a digit); otherwise serialization fails with `Error::InvalidJsonpCallback`.
+    pub jsonp_callback: Option<&'static str>,
+
+    /// How to serialize NaN and infinite floats, which can't be represented in JSON
+    ///
+    /// By default they are rejected with `Error::InvalidFloat`.
+    pub non_finite: NonFinitePolicy,
+
+    /// Called after each element of an array, at any depth, to write extra text into the output
+    ///
+    /// E.g. a hook that writes `|` turns `[1,2]` into `[1|,2|]`. Whatever the hook writes is copied
+    /// verbatim, so the output is no longer JSON unless the hook writes whitespace.
+    pub on_element_end: Option<Hook>,
+
+    /// Serialize arrays whose elements are all `u8`, or all `u16`, as a base64 string
+    ///
+    /// The string is a header that gives the type of the elements, `u8:` or `u16:`, followed by
+    /// the base64 encoding (RFC 4648 alphabet, with padding) of the elements; `u16` elements are
+    /// encoded big endian. E.g. `[1u8, 2, 3]` becomes `"u8:AQID"` and `[0x1234u16]` becomes
+    /// `"u16:EjQ="`. Enable `de::DeserializerConfig::pack_arrays` to deserialize them.
+    ///
+    /// The first element decides whether an array is packed; serialization fails with
+    /// `Error::InvalidPackedElement` if a later element has a different type. Empty arrays and
+    /// arrays of other types, including `Option`s, are serialized as usual. `on_element_end` is
+    /// not called for the elements of packed arrays.
+    pub pack_arrays: bool,
+
+    /// Quote integer map keys, e.g. `{"1":true}` for a `BTreeMap<u8, bool>`, like `serde_json`
+    ///
+    /// JSON object keys must be strings, so by default maps whose keys are not strings (or chars,
+    /// or unit variants) are rejected with `Error::KeyMustBeAString`. Other non-string keys, like
+    /// bools and floats, are rejected either way.
+    pub quote_integer_keys: bool,
+
+    /// Whitespace around the `,` and `:` separators; none by default
+    pub separators: Separators,
+
+    /// Round floats to this many significant digits (clamped to `1..=17`)
+    ///
+    /// Like C's `%g`, exponential notation is used when the exponent is less than -4 or not less
+    /// than the number of digits, and trailing zeros are dropped; e.g. `12345.678` is emitted as
+    /// `1.23e4` and `42.0` as `42` with 3 significant digits. `None` emits the shortest
+    /// representation that round trips.
+    pub significant_digits: Option<usize>,
+
+    /// Omit struct fields whose value serializes to `""`, the same as `None` fields marked with
+    /// `skip_serializing_if`
+    ///
+    /// Only fields of structs are omitted; empty strings in arrays, maps and at the top level are
+    /// still serialized as `""`.
+    pub skip_empty_strings: bool,
+
+    /// Replace arrays and objects nested deeper than this many levels by `truncate_placeholder`
+    ///
+    /// E.g. with a depth of 1 `{"a":{"b":1},"c":2}` becomes `{"a":"...","c":2}`. This bounds the
+    /// size of e.g. log messages; the output can't be deserialized back into the original type.
+    pub truncate_depth: Option<usize>,
+
+    /// String serialized in place of truncated arrays and objects, `"..."` if `None`
+    pub truncate_placeholder: Option<&'static str>,
+}
+
+/// Where to insert a single space around the separators of arrays and objects
+///
+/// Unlike pretty printing this never inserts newlines so the output stays on a single line
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Separators {
+    /// Insert a space before each `,`
+    pub space_before_comma: bool,
+    /// Insert a space after each `,`
+    pub space_after_comma: bool,
+    /// Insert a space before each `:`
+    pub space_before_colon: bool,
+    /// Insert a space after each `:`
+    pub space_after_colon: bool,
+}
+
+impl Separators {
+    /// No whitespace at all, e.g. `{"a":1,"b":[1,2]}`; this is the default
+    pub const COMPACT: Separators = Separators {
+        space_before_comma: false,
+        space_after_comma: false,
+        space_before_colon: false,
+        space_after_colon: false,
+    };
+
+    /// A space after each `,` and `:`, e.g. `{"a": 1, "b": [1, 2]}`
+    pub const SPACED: Separators = Separators {
+        space_before_comma: false,
+        space_after_comma: true,
+        space_before_colon: false,
+        space_after_colon: true,
+    };
+}
+
+/// Layout of pretty printed output, see `to_string_pretty_with`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrettyConfig {
+    /// Indentation of each nesting level, two spaces by default
+    ///
+    /// E.g. `"\t"` or `"    "`. This is copied verbatim so it should only contain whitespace.
+    pub indent: &'static str,
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        PrettyConfig { indent: "  " }
+    }
+}
+
+/// Representation of byte strings, see `SerializerConfig::bytes_format`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BytesFormat {
+    /// An array of numbers, e.g. `[1,2,3]`, like `serde_json`
+    #[default]
+    Array,
+    /// A base64 string (RFC 4648 alphabet, with padding), e.g. `"AQID"`
+    ///
+    /// This takes a third of the space of the bytes plus the quotes, whereas an array takes up to
+    /// 4 bytes per byte.
+    Base64,
+    /// A data URI with this MIME type and the base64 encoded bytes, e.g.
+    /// `"data:image/png;base64,AQID"`
+    ///
+    /// Handy to embed small images or files in web pages. The MIME type is copied verbatim so it
+    /// must not contain characters that need escaping in a JSON string.
+    DataUri(&'static str),
+    /// A lower case hex string, e.g. `"0102ff"`
+    ///
+    /// Easier to read than base64 when debugging, at the cost of 2 characters per byte.
+    Hex,
+}
+
+/// How control characters are escaped, see `SerializerConfig::escape_all_control`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ControlEscapes {
+    /// Write `\b`, `\t`, `\n`, `\f` and `\r` instead of their `\u00XX` form; `true` by default
+    pub use_short_escapes: bool,
+}
+
+impl Default for ControlEscapes {
+    fn default() -> Self {
+        ControlEscapes {
+            use_short_escapes: true,
+        }
+    }
+}
+
+/// Notation used to serialize floats
+///
+/// All of them produce the shortest representation that round trips
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FloatFormat {
+    /// Decimal notation, unless the number is very large or very small
+    ///
+    /// E.g. `1234.5`, `1e16` and `1e-7`. Like `SerdeJson`, `f32`s use exponential notation from
+    /// `1e13` up and below `1e-6`, `f64`s from `1e16` up and below `1e-5`.
+    #[default]
+    Auto,
+    /// Always use decimal notation, e.g. `1234.5`, `10000000000000000` and `0.0000001`
+    ///
+    /// This can be very long: `f64::MAX` expands into more than 300 digits
+    Decimal,
+    /// Always use scientific notation, e.g. `1.2345e3`, `1e16` and `1e-7`
+    Scientific,
+    /// Exactly what `serde_json` emits: like `Auto` but integral values keep a `.0` fraction
+    ///
+    /// E.g. `42.0`, `1e+21` and `1e-7`, so floats stay distinguishable from integers. Like
+    /// `serde_json`, `f32`s use exponential notation from `1e13` up and below `1e-6`, `f64`s from
+    /// `1e16` up and below `1e-5`.
+    SerdeJson,
+}
+
+/// A function that writes into the serializer output, see `SerializerConfig::on_element_end`
+///
+/// Errors returned by the function are reported as `Error::BufferFull`.
+#[derive(Clone, Copy)]
+pub struct Hook(pub fn(&mut dyn Write) -> ::core::fmt::Result);
+
+impl ::core::fmt::Debug for Hook {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        f.write_str("Hook")
+    }
+}
+
+/// What to emit for NaN and infinite floats
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NonFinitePolicy {
+    /// Fail with `Error::InvalidFloat`
+    #[default]
+    Reject,
+    /// Emit whatever `Display` produces, e.g. `NaN` or `inf`
+    ///
+    /// This is not valid JSON
+    Unchecked,
+    /// Emit the JavaScript literals `NaN`, `Infinity` and `-Infinity`
+    ///
+    /// This is not valid JSON either but some non-strict parsers, e.g. JavaScript's `eval` or
+    /// Python's `json` module, accept it
+    AsLiteral,
+    /// Emit `null`, like JavaScript's `JSON.stringify` does
+    Null,
+}
+
+/// Writes JSON text into a `Buffer`; the `serde::Serializer` behind all the `to_*` functions
+pub(crate) struct Emitter<B>
+where
+    B: Buffer,
+{
+    buf: B,
+    config: SerializerConfig,
+    /// Number of arrays and objects currently open
+    depth: usize,
+    /// Element captured at `pack_slot`, see `SerializeSeq`
+    packed: Option<Packed>,
+    /// Buffer position at which a `u8` or `u16` element is captured instead of being written
+    pack_slot: Option<usize>,
+    /// Buffer position at which the content of the innermost `Some` starts
+    some_start: Option<usize>,
+    /// Sort the entries of maps by key, see `to_vec_canonical`
+    sort_keys: bool,
+}
+
+impl<B> Emitter<B>
+where
+    B: Buffer,
+{
+    fn new(buf: B, config: SerializerConfig) -> Self {
+        Emitter {
+            buf,
+            config,
+            depth: 0,
+            packed: None,
+            pack_slot: None,
+            some_start: None,
+            sort_keys: false,
+        }
+    }
+
+    /// Writes the separator between two elements of an array or object
+    fn comma(&mut self) -> Result<()> {
+        if self.config.indent.is_some() {
+            self.buf.push(b',')?;
+            return self.newline();
+        }
+
+        let separators = self.config.separators;
+        self.separator(b',', separators.space_before_comma, separators.space_after_comma)
+    }
+
+    /// Writes the separator between an object key and its value
+    fn colon(&mut self) -> Result<()> {
+        if self.config.indent.is_some() {
+            return self.buf.extend_from_slice(b": ");
+        }
+
+        let separators = self.config.separators;
+        self.separator(b':', separators.space_before_colon, separators.space_after_colon)
+    }
+
+    /// Starts a new line indented to the current depth, when pretty printing
+    fn newline(&mut self) -> Result<()> {
+        if let Some(indent) = self.config.indent {
+            self.buf.push(b'\n')?;
+            for _ in 0..self.depth {
+                self.buf.extend_from_slice(indent.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn separator(&mut self, separator: u8, space_before: bool, space_after: bool) -> Result<()> {
+        if space_before {
+            self.buf.push(b' ')?;
+        }
+        self.buf.push(separator)?;
+        if space_after {
+            self.buf.push(b' ')?;
+        }
+        Ok(())
+    }
+
+    /// Writes `s`, escaping the characters that can't appear verbatim in a JSON string
+    fn escaped(&mut self, s: &str) -> Result<()> {
+        let bytes = s.as_bytes();
+        let (escape_del, short) = match self.config.escape_all_control {
+            Some(escapes) => (true, escapes.use_short_escapes),
+            None => (false, true),
+        };
+
+        // copy the runs of bytes that need no escaping in one go
+        let mut start = 0;
+        let mut unicode = *b"\\u0000";
+        for (i, byte) in bytes.iter().enumerate() {
+            let escape: &[u8] = match *byte {
+                b'"' => b"\\\"",
+                b'\\' => b"\\\\",
+                b'\n' if short => b"\\n",
+                b'\r' if short => b"\\r",
+                b'\t' if short => b"\\t",
+                0x08 if short => b"\\b",
+                0x0c if short => b"\\f",
+                // the remaining control characters; uppercase like `serde_json`
+                0x00..=0x1f | 0x7f if *byte < 0x20 || escape_del => {
+                    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+                    unicode[4] = HEX[(byte >> 4) as usize];
+                    unicode[5] = HEX[(byte & 0xf) as usize];
+                    &unicode
+                }
+                _ => continue,
+            };
+
+            self.buf.extend_from_slice(&bytes[start..i])?;
+            self.buf.extend_from_slice(escape)?;
+            start = i + 1;
+        }
+
+        self.buf.extend_from_slice(&bytes[start..])?;
+        Ok(())
+    }
+
+    /// Writes the quoted name of a struct field or enum variant
+    ///
+    /// Names almost never need escaping, so they are copied verbatim unless they contain a `"`,
+    /// a `\\` or a control character, e.g. when renamed with `#[serde(rename = "...")]`
+    fn key(&mut self, key: &str) -> Result<()> {
+        self.buf.push(b'"')?;
+        if key.bytes().any(|b| b == b'"' || b == b'\\' || b < 0x20 || b == 0x7f) {
+            self.escaped(key)?;
+        } else {
+            self.buf.extend_from_slice(key.as_bytes())?;
+        }
+        self.buf.push(b'"')
+    }
+
+    /// Runs the `on_element_end` hook, if any
+    fn element_end(&mut self) -> Result<()> {
+        match self.config.on_element_end {
+            // NOTE our `fmt::Write` implementation only fails when the buffer is full
+            Some(Hook(hook)) => hook(self).map_err(|_| self.buf.full(1)),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the `{"variant":` that opens an externally tagged enum variant
+    ///
+    /// Returns whether the variant was written as the truncation placeholder instead
+    fn open_variant(&mut self, variant: &str) -> Result<bool> {
+        if self.truncate()? {
+            return Ok(true);
+        }
+
+        self.buf.push(b'{')?;
+        self.newline()?;
+        self.key(variant)?;
+        self.colon()?;
+        Ok(false)
+    }
+
+    /// Closes the object opened by `open_variant`
+    fn close_variant(&mut self) -> Result<()> {
+        self.depth -= 1;
+        self.newline()?;
+        self.buf.push(b'}')
+    }
+
+    /// Writes the truncation placeholder if an array or object opened now would be nested deeper
+    /// than `truncate_depth`
+    ///
+    /// Returns whether the container must be skipped; otherwise it's accounted as open
+    fn truncate(&mut self) -> Result<bool> {
+        match self.config.truncate_depth {
+            Some(depth) if self.depth >= depth => {
+                let placeholder = self.config.truncate_placeholder.unwrap_or("...");
+                ser::Serializer::serialize_str(self, placeholder)?;
+                Ok(true)
+            }
+            _ => {
+                self.depth += 1;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Writes the formatted float `s`, signing positive exponents like `serde_json`, e.g. `1e+16`
+    fn signed_exponent(&mut self, s: &str) -> Result<()> {
+        match s.find('e') {
+            Some(i) if !s[i + 1..].starts_with('-') => {
+                self.buf.extend_from_slice(&s.as_bytes()[..=i])?;
+                self.buf.push(b'+')?;
+                self.buf.extend_from_slice(&s.as_bytes()[i + 1..])
+            }
+            _ => self.buf.extend_from_slice(s.as_bytes()),
+        }
+    }
+
+    /// Writes the finite float `v` rounded to `digits` significant digits
+    fn significant<F>(&mut self, v: F, digits: usize) -> Result<()>
+    where
+        F: ::core::fmt::Display + ::core::fmt::LowerExp,
+    {
+        // f64 has at most 17 significant decimal digits
+        let digits = digits.clamp(1, 17);
+
+        // "-1.2345678901234567e-308"
+        let mut s: String<24> = String::new();
+        write!(s, "{:.*e}", digits - 1, v).unwrap();
+
+        let (mantissa, exponent) = s.split_at(s.find('e').unwrap());
+        let exp: i32 = exponent[1..].parse().unwrap();
+
+        if exp < -4 || exp >= digits as i32 {
+            self.buf.extend_from_slice(trim_fraction(mantissa).as_bytes())?;
+            self.buf.extend_from_slice(exponent.as_bytes())?;
+        } else {
+            // "-0.00012345678901234567"
+            let mut s: String<24> = String::new();
+            write!(s, "{:.*}", (digits as i32 - 1 - exp) as usize, v).unwrap();
+
+            self.buf.extend_from_slice(trim_fraction(&s).as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Computes the CRC-32 (IEEE) of `bytes`
+///
+/// This is the bitwise implementation; a lookup table would take 1 KB of ROM / Flash
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                crc >> 1 ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Checks that `s` is a JavaScript identifier made of ASCII characters
+fn is_identifier(s: &str) -> bool {
+    let mut bytes = s.bytes();
+
+    match bytes.next() {
+        Some(b'a'..=b'z') | Some(b'A'..=b'Z') | Some(b'_') | Some(b'$') => {}
+        _ => return false,
+    }
+
+    bytes.all(|b| matches!(b, b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'$'))
+}
+
+/// Drops the trailing zeros of a decimal fraction, and the decimal point if nothing is left
+fn trim_fraction(s: &str) -> &str {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        s
     }
 }
 
-pub(crate) struct Serializer<B>
+impl<B> ::core::fmt::Write for Emitter<B>
 where
-    B: Unsize<[u8]>,
+    B: Buffer,
 {
-    buf: Vec<u8, B>,
+    fn write_str(&mut self, s: &str) -> ::core::fmt::Result {
+        self.buf
+            .extend_from_slice(s.as_bytes())
+            .map_err(|_| ::core::fmt::Error)
+    }
 }
 
-impl<B> Serializer<B>
+/// `fmt::Write` adapter that escapes what's written into it, see `Emitter::escaped`
+struct Escaper<'a, B>(&'a mut Emitter<B>)
 where
-    B: Unsize<[u8]>,
+    B: Buffer + 'a;
+
+impl<'a, B> ::core::fmt::Write for Escaper<'a, B>
+where
+    B: Buffer,
 {
-    fn new() -> Self {
-        Serializer { buf: Vec::new() }
+    fn write_str(&mut self, s: &str) -> ::core::fmt::Result {
+        self.0.escaped(s).map_err(|_| ::core::fmt::Error)
     }
 }
 
@@ -64,7 +660,7 @@ where
 // which take 200+ bytes of ROM / Flash
 macro_rules! serialize_unsigned {
     ($self:ident, $N:expr, $v:expr) => {{
-        let mut buf: [u8; $N] = unsafe { mem::uninitialized() };
+        let mut buf = [0u8; $N];
 
         let mut v = $v;
         let mut i = $N - 1;
@@ -87,15 +683,15 @@ macro_rules! serialize_unsigned {
 macro_rules! serialize_signed {
     ($self:ident, $N:expr, $v:expr, $ixx:ident, $uxx:ident) => {{
         let v = $v;
-        let (signed, mut v) = if v == $ixx::min_value() {
-            (true, $ixx::max_value() as $uxx + 1)
+        let (signed, mut v) = if v == $ixx::MIN {
+            (true, $ixx::MAX as $uxx + 1)
         } else if v < 0 {
             (true, -v as $uxx)
         } else {
             (false, v as $uxx)
         };
 
-        let mut buf: [u8; $N] = unsafe { mem::uninitialized() };
+        let mut buf = [0u8; $N];
         let mut i = $N - 1;
         loop {
             buf[i] = (v % 10) as u8 + b'0';
@@ -118,19 +714,109 @@ macro_rules! serialize_signed {
     }};
 }
 
-impl<'a, B> ser::Serializer for &'a mut Serializer<B>
+// NOTE(serialize_float) `Display` never switches to exponential notation so very large or very
+// small values would expand into hundreds of digits; those are formatted with `{:e}` instead. Both
+// formats produce the shortest representation that round trips. `$min..$max` is the range of
+// magnitudes that `serde_json` writes in decimal notation, see `FloatFormat::SerdeJson`
+macro_rules! serialize_float {
+    ($self:ident, $v:expr, $min:expr, $max:expr) => {{
+        let v = $v;
+
+        if !v.is_finite() {
+            match $self.config.non_finite {
+                NonFinitePolicy::Reject => return Err(Error::InvalidFloat),
+                NonFinitePolicy::Unchecked => {}
+                NonFinitePolicy::AsLiteral => {
+                    let literal: &[u8] = if v.is_nan() {
+                        b"NaN"
+                    } else if v > 0. {
+                        b"Infinity"
+                    } else {
+                        b"-Infinity"
+                    };
+
+                    $self.buf.extend_from_slice(literal)?;
+                    return Ok(());
+                }
+                NonFinitePolicy::Null => {
+                    $self.buf.extend_from_slice(b"null")?;
+                    return Ok(());
+                }
+            }
+        }
+
+        match ($self.config.significant_digits, $self.config.float_format) {
+            (Some(digits), _) if v.is_finite() => $self.significant(v, digits),
+            // NOTE `ryu` switches to exponential notation at `$min` and `$max`, which differ
+            // between `f32` and `f64`; the `write!` path below uses the same thresholds so the
+            // output doesn't depend on the `ryu` feature
+            #[cfg(feature = "ryu")]
+            (_, FloatFormat::Auto) => {
+                let mut buffer = ryu::Buffer::new();
+                let s = buffer.format(v);
+                // `ryu` writes integral values as e.g. `1.0`; drop the fraction like `Display`
+                let s = s.strip_suffix(".0").unwrap_or(s);
+
+                $self.buf.extend_from_slice(s.as_bytes())?;
+                Ok(())
+            }
+            // NOTE all the notations are written straight into `buf`; `Decimal` can be hundreds of
+            // bytes long
+            #[cfg(not(feature = "ryu"))]
+            (_, FloatFormat::Auto) => {
+                let abs = if v < 0. { -v } else { v };
+
+                if v == 0. || ($min..$max).contains(&abs) {
+                    write!($self, "{}", v).map_err(|_| $self.buf.full(1))
+                } else {
+                    write!($self, "{:e}", v).map_err(|_| $self.buf.full(1))
+                }
+            }
+            #[cfg(feature = "ryu")]
+            (_, FloatFormat::SerdeJson) if v.is_finite() => {
+                let mut buffer = ryu::Buffer::new();
+                $self.signed_exponent(buffer.format_finite(v))
+            }
+            #[cfg(not(feature = "ryu"))]
+            (_, FloatFormat::SerdeJson) if v.is_finite() => {
+                let abs = if v < 0. { -v } else { v };
+
+                if v == 0. || ($min..$max).contains(&abs) {
+                    write!($self, "{}", v).map_err(|_| $self.buf.full(1))?;
+                    if v % 1. == 0. {
+                        $self.buf.extend_from_slice(b".0")?;
+                    }
+                    Ok(())
+                } else {
+                    // "-1.2345678901234567e-308"
+                    let mut s: String<24> = String::new();
+                    write!(s, "{:e}", v).unwrap();
+                    $self.signed_exponent(&s)
+                }
+            }
+            (_, FloatFormat::Decimal) | (_, FloatFormat::SerdeJson) => {
+                write!($self, "{}", v).map_err(|_| $self.buf.full(1))
+            }
+            (_, FloatFormat::Scientific) => {
+                write!($self, "{:e}", v).map_err(|_| $self.buf.full(1))
+            }
+        }
+    }};
+}
+
+impl<'a, B> ser::Serializer for &'a mut Emitter<B>
 where
-    B: Unsize<[u8]>,
+    B: Buffer,
 {
     type Ok = ();
     type Error = Error;
     type SerializeSeq = SerializeSeq<'a, B>;
     type SerializeTuple = SerializeSeq<'a, B>;
-    type SerializeTupleStruct = Unreachable;
-    type SerializeTupleVariant = Unreachable;
-    type SerializeMap = Unreachable;
+    type SerializeTupleStruct = SerializeSeq<'a, B>;
+    type SerializeTupleVariant = SerializeSeq<'a, B>;
+    type SerializeMap = SerializeMap<'a, B>;
     type SerializeStruct = SerializeStruct<'a, B>;
-    type SerializeStructVariant = Unreachable;
+    type SerializeStructVariant = SerializeStruct<'a, B>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
         if v {
@@ -163,11 +849,21 @@ where
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
+        if self.pack_slot == Some(self.buf.len()) {
+            self.packed = Some(Packed::U8(v));
+            return Ok(());
+        }
+
         // "255"
         serialize_unsigned!(self, 3, v)
     }
 
     fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
+        if self.pack_slot == Some(self.buf.len()) {
+            self.packed = Some(Packed::U16(v));
+            return Ok(());
+        }
+
         // "65535"
         serialize_unsigned!(self, 5, v)
     }
@@ -182,27 +878,69 @@ where
         serialize_unsigned!(self, 20, v)
     }
 
-    fn serialize_f32(self, _v: f32) -> Result<Self::Ok> {
-        unreachable!()
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
+        serialize_float!(self, v, 1e-6, 1e13)
     }
 
-    fn serialize_f64(self, _v: f64) -> Result<Self::Ok> {
-        unreachable!()
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
+        serialize_float!(self, v, 1e-5, 1e16)
     }
 
-    fn serialize_char(self, _v: char) -> Result<Self::Ok> {
-        unreachable!()
+    fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        let mut buf = [0; 4];
+        self.serialize_str(v.encode_utf8(&mut buf))
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
         self.buf.push(b'"')?;
-        self.buf.extend_from_slice(v.as_bytes())?;
+        self.escaped(v)?;
         self.buf.push(b'"')?;
         Ok(())
     }
 
-    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok> {
-        unreachable!()
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        let mime = match self.config.bytes_format {
+            BytesFormat::Array => {
+                // like `serde_json`, an array of numbers
+                let mut seq = self.serialize_seq(Some(v.len()))?;
+                for byte in v {
+                    ser::SerializeSeq::serialize_element(&mut seq, byte)?;
+                }
+                return ser::SerializeSeq::end(seq);
+            }
+            BytesFormat::Base64 => None,
+            BytesFormat::DataUri(mime) => Some(mime),
+            BytesFormat::Hex => {
+                const HEX: &[u8; 16] = b"0123456789abcdef";
+
+                self.buf.push(b'"')?;
+                for byte in v {
+                    self.buf.push(HEX[(byte >> 4) as usize])?;
+                    self.buf.push(HEX[(byte & 0xf) as usize])?;
+                }
+                return self.buf.push(b'"');
+            }
+        };
+
+        self.buf.push(b'"')?;
+        if let Some(mime) = mime {
+            self.buf.extend_from_slice(b"data:")?;
+            self.buf.extend_from_slice(mime.as_bytes())?;
+            self.buf.extend_from_slice(b";base64,")?;
+        }
+
+        let mut encoder = Encoder::new();
+        for byte in v {
+            if let Some(chars) = encoder.push(*byte) {
+                self.buf.extend_from_slice(&chars)?;
+            }
+        }
+        if let Some(chars) = encoder.finish() {
+            self.buf.extend_from_slice(&chars)?;
+        }
+
+        self.buf.push(b'"')?;
+        Ok(())
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
@@ -210,19 +948,25 @@ where
         Ok(())
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: ser::Serialize,
+        T: ser::Serialize + ?Sized,
     {
-        value.serialize(self)
+        // `Some(_)` elements are never packed
+        self.pack_slot = None;
+
+        let some_start = self.some_start.replace(self.buf.len());
+        let ret = value.serialize(&mut *self);
+        self.some_start = some_start;
+        ret
     }
 
     fn serialize_unit(self) -> Result<Self::Ok> {
-        unreachable!()
+        self.buf.extend_from_slice(b"null")
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
-        unreachable!()
+        self.serialize_unit()
     }
 
     fn serialize_unit_variant(
@@ -234,34 +978,54 @@ where
         self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T>(
         self,
         _name: &'static str,
-        _value: &T,
+        value: &T,
     ) -> Result<Self::Ok>
     where
-        T: ser::Serialize,
+        T: ser::Serialize + ?Sized,
     {
-        unreachable!()
+        value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
+        variant: &'static str,
+        value: &T,
     ) -> Result<Self::Ok>
     where
-        T: ser::Serialize,
+        T: ser::Serialize + ?Sized,
     {
-        unreachable!()
+        if self.open_variant(variant)? {
+            return Ok(());
+        }
+
+        value.serialize(&mut *self)?;
+        self.close_variant()
     }
 
-    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        // nothing has been written since the `Some` started so this sequence is its whole content
+        if self.config.empty_collection_as_null
+            && len == Some(0)
+            && self.some_start == Some(self.buf.len())
+        {
+            self.buf.extend_from_slice(b"null")?;
+
+            return Ok(SerializeSeq::skip(self));
+        }
+
+        if self.truncate()? {
+            return Ok(SerializeSeq::skip(self));
+        }
+
+        let start = self.buf.len();
         self.buf.push(b'[')?;
 
-        Ok(SerializeSeq::new(self))
+        Ok(SerializeSeq::new(self, start))
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
@@ -271,122 +1035,513 @@ where
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        unreachable!()
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        unreachable!()
+        if self.open_variant(variant)? {
+            return Ok(SerializeSeq::skip(self));
+        }
+
+        Ok(self.serialize_seq(Some(len))?.variant())
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        unreachable!()
+        if self.truncate()? {
+            return Ok(SerializeMap::skip(self));
+        }
+
+        self.buf.push(b'{')?;
+
+        Ok(SerializeMap::new(self))
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        if self.truncate()? {
+            return Ok(SerializeStruct::skip(self));
+        }
+
+        let version = if self.depth == 1 {
+            self.config.inject_version
+        } else {
+            None
+        };
+
+        let start = self.buf.len();
         self.buf.push(b'{')?;
 
-        Ok(SerializeStruct::new(self))
+        let mut state = SerializeStruct::new(self, start);
+        if let Some((key, version)) = version {
+            ser::SerializeStruct::serialize_field(&mut state, key, &version)?;
+        }
+
+        Ok(state)
     }
 
     fn serialize_struct_variant(
         self,
-        _name: &'static str,
+        name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        unreachable!()
+        if self.open_variant(variant)? {
+            return Ok(SerializeStruct::skip(self));
+        }
+
+        Ok(self.serialize_struct(name, len)?.variant())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        !self.config.binary
     }
 
-    fn collect_str<T: ?Sized>(self, _value: &T) -> Result<Self::Ok>
+    fn collect_str<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: fmt::Display,
+        T: ::core::fmt::Display + ?Sized,
     {
-        unreachable!()
+        self.buf.push(b'"')?;
+        // NOTE the only error `Escaper` reports is a full buffer
+        write!(Escaper(self), "{}", value).map_err(|_| self.buf.full(1))?;
+        self.buf.push(b'"')?;
+        Ok(())
     }
 }
 
-/// Serializes the given data structure as a string of JSON text
-pub fn to_string<B, T>(value: &T) -> Result<String<B>>
+/// Serializes the given data structure into `buf` and returns the number of bytes written
+///
+/// Returns `Error::BufferFull` if the JSON doesn't fit in `buf`; its contents are then unspecified.
+pub fn to_slice<T>(value: &T, buf: &mut [u8]) -> Result<usize>
 where
-    B: Unsize<[u8]>,
     T: ser::Serialize + ?Sized,
 {
-    let mut ser = Serializer::new();
-    value.serialize(&mut ser)?;
-    Ok(unsafe { String::from_utf8_unchecked(ser.buf) })
+    to_slice_with_options(value, buf, SerializerConfig::default())
 }
 
-/// Serializes the given data structure as a JSON byte vector
-pub fn to_vec<B, T>(value: &T) -> Result<Vec<u8, B>>
+/// Serializes the given data structure into `buf` using the given configuration and returns the
+/// number of bytes written
+pub fn to_slice_with_options<T>(
+    value: &T,
+    buf: &mut [u8],
+    config: SerializerConfig,
+) -> Result<usize>
 where
-    B: Unsize<[u8]>,
     T: ser::Serialize + ?Sized,
 {
-    let mut ser = Serializer::new();
-    value.serialize(&mut ser)?;
-    Ok(ser.buf)
+    Ok(serialize(value, Cursor::new(buf), config)?.len())
 }
 
-impl ser::Error for Error {
-    fn custom<T>(_msg: T) -> Self
-    where
-        T: fmt::Display,
-    {
-        unreachable!()
-    }
+/// Serializes the given data structure into a sequence of `segments`, e.g. for scatter-gather I/O
+///
+/// The segments are filled in order: the output spills into the next segment when one is full.
+/// Returns the number of bytes written into each segment; only the last non-empty segment can be
+/// partially filled. Returns `Error::BufferFull` if the JSON doesn't fit in all the segments
+/// together; their contents are then unspecified.
+pub fn to_slices<const S: usize, T>(value: &T, segments: &mut [&mut [u8]; S]) -> Result<[usize; S]>
+where
+    T: ser::Serialize + ?Sized,
+{
+    Ok(serialize(value, Segments::new(segments), SerializerConfig::default())?.counts())
 }
 
-pub(crate) enum Unreachable {}
-
-impl ser::SerializeTupleStruct for Unreachable {
-    type Ok = ();
-    type Error = Error;
+/// Serializes the given data structure as a string of JSON text
+pub fn to_string<const N: usize, T>(value: &T) -> Result<String<N>>
+where
+    T: ser::Serialize + ?Sized,
+{
+    to_string_with_options(value, SerializerConfig::default())
+}
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()> {
-        unreachable!()
+/// Serializes the given data structure as a string of JSON text, measuring it first
+///
+/// Unlike `to_string`, whose `Error::BufferFull` only gives a lower bound of the size the string
+/// needs, this serializes the value twice: a first pass counts the bytes of the JSON text, and
+/// if they don't fit in `N` bytes the error reports their exact number as `needed`.
+pub fn to_string_exact<const N: usize, T>(value: &T) -> Result<String<N>>
+where
+    T: ser::Serialize + ?Sized,
+{
+    let needed = serialize(value, Counter::new(), SerializerConfig::default())?.len();
+    if needed > N {
+        return Err(Error::BufferFull {
+            capacity: N,
+            needed,
+        });
     }
 
-    fn end(self) -> Result<Self::Ok> {
-        unreachable!()
-    }
+    to_string(value)
 }
 
-impl ser::SerializeTupleVariant for Unreachable {
-    type Ok = ();
-    type Error = Error;
+/// Serializes the given data structure as a string of JSON text using the given configuration
+pub fn to_string_with_options<const N: usize, T>(
+    value: &T,
+    config: SerializerConfig,
+) -> Result<String<N>>
+where
+    T: ser::Serialize + ?Sized,
+{
+    let buf = to_vec_with_options(value, config)?;
+    Ok(into_string(buf))
+}
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()> {
-        unreachable!()
-    }
+/// Converts serialized JSON text, which is always valid UTF-8, into a `String`
+fn into_string<const N: usize>(buf: Vec<u8, N>) -> String<N> {
+    let mut string = String::new();
+    unsafe { *string.as_mut_vec() = buf };
+    string
+}
 
-    fn end(self) -> Result<Self::Ok> {
-        unreachable!()
-    }
+/// Serializes the given data structure as a pretty printed string of JSON text
+///
+/// Arrays and objects are spread over multiple lines and indented by two spaces per level, e.g.
+///
+/// ``` text
+/// {
+///   "led": true,
+///   "rgb": [
+///     255,
+///     0,
+///     0
+///   ]
+/// }
+/// ```
+pub fn to_string_pretty<const N: usize, T>(value: &T) -> Result<String<N>>
+where
+    T: ser::Serialize + ?Sized,
+{
+    to_string_pretty_with(value, PrettyConfig::default())
 }
 
-impl ser::SerializeMap for Unreachable {
-    type Ok = ();
-    type Error = Error;
+/// Serializes the given data structure as a pretty printed string of JSON text using the given
+/// layout
+pub fn to_string_pretty_with<const N: usize, T>(
+    value: &T,
+    pretty: PrettyConfig,
+) -> Result<String<N>>
+where
+    T: ser::Serialize + ?Sized,
+{
+    let buf = to_vec_pretty_with(value, pretty)?;
+    Ok(into_string(buf))
+}
 
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<()>
-    where
-        T: ser::Serialize,
-    {
-        unreachable!()
+/// Serializes the given data structure as a JSON byte vector
+pub fn to_vec<const N: usize, T>(value: &T) -> Result<Vec<u8, N>>
+where
+    T: ser::Serialize + ?Sized,
+{
+    to_vec_with_options(value, SerializerConfig::default())
+}
+
+/// Serializes the given data structure as canonical JSON, e.g. to hash or sign it
+///
+/// This produces the same bytes for values that are logically equal. It's a subset of the JSON
+/// Canonicalization Scheme (RFC 8785): the output is compact, strings use the minimal escaping of
+/// `to_vec` and the entries of maps are sorted by key, whatever order the map iterates in.
+/// Unlike JCS:
+///
+/// - struct fields stay in declaration order, as `#[derive(Serialize)]` emits them; declare them
+///   in sorted order to match JCS
+/// - keys are compared as serialized UTF-8 bytes rather than as UTF-16 code units, which only
+///   orders differently keys with escaped characters or characters above U+FFFF
+/// - floats use `FloatFormat::Auto`, which switches to exponential notation at other thresholds
+///   than JCS' `1e21` and `1e-7`
+pub fn to_vec_canonical<const N: usize, T>(value: &T) -> Result<Vec<u8, N>>
+where
+    T: ser::Serialize + ?Sized,
+{
+    let mut ser = Emitter::new(Vec::new(), SerializerConfig::default());
+    ser.sort_keys = true;
+    value.serialize(&mut ser)?;
+    Ok(ser.buf)
+}
+
+/// Serializes the given data structure as a JSON byte vector using the given configuration
+pub fn to_vec_with_options<const N: usize, T>(
+    value: &T,
+    config: SerializerConfig,
+) -> Result<Vec<u8, N>>
+where
+    T: ser::Serialize + ?Sized,
+{
+    serialize(value, Vec::new(), config)
+}
+
+/// Serializes the given data structure as a pretty printed JSON byte vector, see
+/// `to_string_pretty`
+pub fn to_vec_pretty<const N: usize, T>(value: &T) -> Result<Vec<u8, N>>
+where
+    T: ser::Serialize + ?Sized,
+{
+    to_vec_pretty_with(value, PrettyConfig::default())
+}
+
+/// Serializes the given data structure as a pretty printed JSON byte vector using the given
+/// layout
+pub fn to_vec_pretty_with<const N: usize, T>(value: &T, pretty: PrettyConfig) -> Result<Vec<u8, N>>
+where
+    T: ser::Serialize + ?Sized,
+{
+    let config = SerializerConfig {
+        indent: Some(pretty.indent),
+        ..SerializerConfig::default()
+    };
+
+    to_vec_with_options(value, config)
+}
+
+/// Serializes the given data structure as JSON text into the `writer`
+///
+/// The output is written piecewise as it's produced, so the document is never held in memory as a
+/// whole. Fails with `Error::WriteFailed` if the `writer` returns an error; what it has received
+/// up to that point is then not valid JSON.
+pub fn to_writer<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: ::core::fmt::Write,
+    T: ser::Serialize + ?Sized,
+{
+    serialize(value, Writer::new(writer), SerializerConfig::default())?;
+    Ok(())
+}
+
+/// Serializes the given data structure as JSON text straight into a serial port
+///
+/// Like `to_writer`, each piece of the output is sent as soon as it's produced, blocking until the
+/// port accepts it, and no buffer holds the document. Fails with `Error::WriteFailed` if the port
+/// returns an error. The port is flushed once the whole value has been sent.
+#[cfg(feature = "embedded-hal")]
+pub fn to_serial<S, T>(serial: &mut S, value: &T) -> Result<()>
+where
+    S: ::embedded_hal::blocking::serial::Write<u8>,
+    T: ser::Serialize + ?Sized,
+{
+    serialize(value, Serial::new(serial), SerializerConfig::default())?;
+    serial.bflush().map_err(|_| Error::WriteFailed)
+}
+
+fn serialize<B, T>(value: &T, buf: B, config: SerializerConfig) -> Result<B>
+where
+    B: Buffer,
+    T: ser::Serialize + ?Sized,
+{
+    let mut ser = Emitter::new(buf, config);
+
+    if let Some(callback) = config.jsonp_callback {
+        if !is_identifier(callback) {
+            return Err(Error::InvalidJsonpCallback);
+        }
+
+        ser.buf.extend_from_slice(callback.as_bytes())?;
+        ser.buf.push(b'(')?;
+    }
+
+    value.serialize(&mut ser)?;
+
+    if config.jsonp_callback.is_some() {
+        ser.buf.extend_from_slice(b");")?;
+    }
+
+    Ok(ser.buf)
+}
+
+/// Serializer that keeps its buffer across values, e.g. to send many messages from a loop
+///
+/// Unlike `to_vec`, which returns a new buffer per value, this writes every value into the same
+/// `N` byte buffer; `clear` it in between values to reuse it.
+pub struct Serializer<const N: usize> {
+    buf: Vec<u8, N>,
+    config: SerializerConfig,
+}
+
+impl<const N: usize> Serializer<N> {
+    /// Creates a serializer with an empty buffer and the default configuration
+    pub fn new() -> Self {
+        Serializer::with_options(SerializerConfig::default())
+    }
+
+    /// Creates a serializer with an empty buffer and the given configuration
+    pub fn with_options(config: SerializerConfig) -> Self {
+        Serializer {
+            buf: Vec::new(),
+            config,
+        }
+    }
+
+    /// Appends the JSON text of `value` to the buffer
+    ///
+    /// Values serialized without a `clear` in between are concatenated as is, without a
+    /// separator. On error the buffer is left as it was before the call.
+    pub fn serialize<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        let len = self.buf.len();
+        if let Err(e) = serialize(value, &mut self.buf, self.config) {
+            self.buf.truncate(len);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Empties the buffer so the next value is written at its start
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+
+    /// The JSON text written so far
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// The JSON text written so far
+    pub fn as_str(&self) -> &str {
+        // NOTE(unsafe) the serializer only ever writes UTF-8
+        unsafe { str::from_utf8_unchecked(&self.buf) }
+    }
+}
+
+impl<const N: usize> Default for Serializer<N> {
+    fn default() -> Self {
+        Serializer::new()
+    }
+}
+
+/// Serializes a JSON array whose elements are produced by calling `f` repeatedly
+///
+/// Each call may append any number of elements through the `SeqWriter`; the array is closed once
+/// `f` returns `false`. Unlike collecting the elements first, this doesn't need any storage other
+/// than the output buffer.
+pub fn serialize_array_with<const N: usize, F>(mut f: F) -> Result<Vec<u8, N>>
+where
+    F: FnMut(&mut SeqWriter<Vec<u8, N>>) -> Result<bool>,
+{
+    let mut serializer = Emitter::new(Vec::new(), SerializerConfig::default());
+    {
+        let seq = ser::Serializer::serialize_seq(&mut serializer, None)?;
+        let mut writer = SeqWriter::new(seq);
+        while f(&mut writer)? {}
+        writer.end()?;
+    }
+    Ok(serializer.buf)
+}
+
+impl ser::Error for Error {
+    fn custom<T>(_msg: T) -> Self
+    where
+        T: ::core::fmt::Display,
+    {
+        Error::Custom
+    }
+}
+
+pub(crate) enum Unreachable {}
+
+impl ser::SerializeSeq for Unreachable {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, _value: &T) -> Result<()> {
+        unreachable!()
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        unreachable!()
+    }
+}
+
+impl ser::SerializeTuple for Unreachable {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, _value: &T) -> Result<()> {
+        unreachable!()
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        unreachable!()
+    }
+}
+
+impl ser::SerializeTupleStruct for Unreachable {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()> {
+        unreachable!()
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        unreachable!()
+    }
+}
+
+impl ser::SerializeTupleVariant for Unreachable {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()> {
+        unreachable!()
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        unreachable!()
+    }
+}
+
+impl ser::SerializeMap for Unreachable {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, _key: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        unreachable!()
+    }
+
+    fn serialize_value<T>(&mut self, _value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        unreachable!()
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        unreachable!()
+    }
+}
+
+impl ser::SerializeStruct for Unreachable {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, _value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        unreachable!()
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        unreachable!()
     }
+}
+
+impl ser::SerializeStructVariant for Unreachable {
+    type Ok = ();
+    type Error = Error;
 
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, _key: &'static str, _value: &T) -> Result<()>
     where
-        T: ser::Serialize,
+        T: ser::Serialize + ?Sized,
     {
         unreachable!()
     }
@@ -394,80 +1549,1363 @@ impl ser::SerializeMap for Unreachable {
     fn end(self) -> Result<Self::Ok> {
         unreachable!()
     }
-}
+}
+
+#[cfg(test)]
+mod tests {
+    const N: usize = 128;
+
+    #[test]
+    fn array() {
+        assert_eq!(
+            &*super::to_string::<N, _>(&[0, 1, 2]).unwrap(),
+            "[0,1,2]"
+        );
+    }
+
+    #[test]
+    fn array_of_escaped_str() {
+        let strs: &[&str] = &["a\"b", "c\nd"];
+
+        assert_eq!(
+            &*super::to_string::<N, _>(strs).unwrap(),
+            r#"["a\"b","c\nd"]"#
+        );
+    }
+
+    #[test]
+    fn array_with() {
+        let mut i = 0;
+        let json = super::serialize_array_with::<N, _>(|seq| {
+            seq.element(&i)?;
+            i += 1;
+            Ok(i < 3)
+        }).unwrap();
+        assert_eq!(&json[..], b"[0,1,2]");
+
+        let json = super::serialize_array_with::<N, _>(|_| Ok(false)).unwrap();
+        assert_eq!(&json[..], b"[]");
+    }
+
+    #[test]
+    fn bool() {
+        assert_eq!(&*super::to_string::<N, _>(&true).unwrap(), "true");
+    }
+
+    #[test]
+    fn buffer_full() {
+        use super::Error;
+
+        let readings = [1000u16; 32];
+        match super::to_string::<16, _>(&readings) {
+            Err(Error::BufferFull { capacity, needed }) => {
+                assert_eq!(capacity, 16);
+                assert_eq!(needed, 20);
+            }
+            _ => panic!("expected `BufferFull`"),
+        }
+    }
+
+    #[test]
+    fn bytes() {
+        // what `serde_bytes::Bytes` does
+        struct Bytes<'a>(&'a [u8]);
+
+        impl<'a> ::serde::Serialize for Bytes<'a> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                serializer.serialize_bytes(self.0)
+            }
+        }
+
+        #[derive(Serialize)]
+        struct Frame<'a> {
+            payload: Bytes<'a>,
+        }
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&Frame {
+                payload: Bytes(&[1, 2, 3]),
+            }).unwrap(),
+            r#"{"payload":[1,2,3]}"#
+        );
+        assert_eq!(&*super::to_string::<N, _>(&Bytes(&[])).unwrap(), "[]");
+    }
+
+    #[test]
+    fn bytes_base64() {
+        use base64::Decoder;
+
+        struct Bytes<'a>(&'a [u8]);
+
+        impl<'a> ::serde::Serialize for Bytes<'a> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                serializer.serialize_bytes(self.0)
+            }
+        }
+
+        let config = super::SerializerConfig {
+            bytes_format: super::BytesFormat::Base64,
+            ..super::SerializerConfig::default()
+        };
+
+        for &(bytes, json) in &[
+            (&b""[..], r#""""#),
+            (b"\x01", r#""AQ==""#),
+            (b"\x01\x02", r#""AQI=""#),
+            (b"\x01\x02\x03", r#""AQID""#),
+            (b"\x00\xff\x10\x80", r#""AP8QgA==""#),
+        ] {
+            let s = super::to_string_with_options::<N, _>(&Bytes(bytes), config).unwrap();
+            assert_eq!(&*s, json);
+
+            let mut decoder = Decoder::new(&s.as_bytes()[1..s.len() - 1]);
+            for byte in bytes {
+                assert_eq!(decoder.next(), Ok(Some(*byte)));
+            }
+            assert_eq!(decoder.next(), Ok(None));
+        }
+    }
+
+    #[test]
+    fn bytes_data_uri() {
+        struct Bytes<'a>(&'a [u8]);
+
+        impl<'a> ::serde::Serialize for Bytes<'a> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                serializer.serialize_bytes(self.0)
+            }
+        }
+
+        let config = super::SerializerConfig {
+            bytes_format: super::BytesFormat::DataUri("image/png"),
+            ..super::SerializerConfig::default()
+        };
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&Bytes(b"\x89PNG"), config).unwrap(),
+            r#""data:image/png;base64,iVBORw==""#
+        );
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&Bytes(&[]), config).unwrap(),
+            r#""data:image/png;base64,""#
+        );
+    }
+
+    #[test]
+    fn bytes_hex() {
+        struct Bytes<'a>(&'a [u8]);
+
+        impl<'a> ::serde::Serialize for Bytes<'a> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                serializer.serialize_bytes(self.0)
+            }
+        }
+
+        let config = super::SerializerConfig {
+            bytes_format: super::BytesFormat::Hex,
+            ..super::SerializerConfig::default()
+        };
+
+        for &(bytes, json) in &[
+            (&b""[..], r#""""#),
+            (b"\x01", r#""01""#),
+            (b"\x00\xff\x10\xab", r#""00ff10ab""#),
+        ] {
+            assert_eq!(
+                &*super::to_string_with_options::<N, _>(&Bytes(bytes), config).unwrap(),
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn char() {
+        assert_eq!(&*super::to_string::<N, _>(&'x').unwrap(), r#""x""#);
+        assert_eq!(
+            &*super::to_string::<N, _>(&['a', 'b', 'é']).unwrap(),
+            r#"["a","b","é"]"#
+        );
+    }
+
+    #[test]
+    fn char_escaped() {
+        assert_eq!(
+            &*super::to_string::<N, _>(&['"', '\n']).unwrap(),
+            r#"["\"","\n"]"#
+        );
+    }
+
+    #[test]
+    fn collect_str() {
+        use core::fmt;
+
+        use serde::{Serialize, Serializer};
+
+        // formatted like `time::Date`
+        struct Date {
+            year: u16,
+            month: u8,
+            day: u8,
+        }
+
+        impl fmt::Display for Date {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+            }
+        }
+
+        impl Serialize for Date {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.collect_str(self)
+            }
+        }
+
+        #[derive(Serialize)]
+        struct Log {
+            date: Date,
+            level: u8,
+        }
+
+        let log = Log {
+            date: Date {
+                year: 2019,
+                month: 3,
+                day: 7,
+            },
+            level: 2,
+        };
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&log).unwrap(),
+            r#"{"date":"2019-03-07","level":2}"#
+        );
+        assert!(super::to_string::<16, _>(&log).is_err());
+    }
+
+    #[test]
+    fn crc_field() {
+        use super::SerializerConfig;
+
+        #[derive(Serialize)]
+        struct Reading {
+            id: u8,
+            temperature: f32,
+        }
+
+        let config = SerializerConfig {
+            crc_field: Some("crc"),
+            ..SerializerConfig::default()
+        };
+
+        let reading = Reading {
+            id: 7,
+            temperature: 21.5,
+        };
+        let json = super::to_string_with_options::<N, _>(&reading, config).unwrap();
+        assert_eq!(&*json, r#"{"id":7,"temperature":21.5,"crc":3221871975}"#);
+
+        // computed with Python's `zlib.crc32`
+        assert_eq!(super::crc32(br#"{"id":7,"temperature":21.5"#), 3221871975);
+        assert_eq!(super::crc32(b"123456789"), 0xcbf4_3926);
+
+        // nested structs don't get a CRC
+        let readings = [Reading {
+            id: 1,
+            temperature: 0.,
+        }];
+        let json = super::to_string_with_options::<N, _>(&readings, config).unwrap();
+        assert_eq!(&*json, r#"[{"id":1,"temperature":0}]"#);
+    }
+
+    #[test]
+    fn empty_collection_as_null() {
+        use super::SerializerConfig;
+
+        #[derive(Serialize)]
+        struct Samples<'a> {
+            samples: Option<&'a [u8]>,
+        }
+
+        let config = SerializerConfig {
+            empty_collection_as_null: true,
+            ..SerializerConfig::default()
+        };
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&Samples { samples: None }, config)
+                .unwrap(),
+            r#"{"samples":null}"#
+        );
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(
+                &Samples {
+                    samples: Some(&[][..]),
+                },
+                config
+            ).unwrap(),
+            r#"{"samples":null}"#
+        );
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(
+                &Samples {
+                    samples: Some(&[1][..]),
+                },
+                config
+            ).unwrap(),
+            r#"{"samples":[1]}"#
+        );
+
+        // only the direct content of the `Option` is affected
+        let nested: Option<&[&[u8]]> = Some(&[&[][..]]);
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&nested, config).unwrap(),
+            "[[]]"
+        );
+
+        // off by default
+        assert_eq!(
+            &*super::to_string::<N, _>(&Samples {
+                samples: Some(&[][..]),
+            }).unwrap(),
+            r#"{"samples":[]}"#
+        );
+    }
+
+    #[test]
+    fn enum_() {
+        #[derive(Serialize)]
+        enum Type {
+            #[serde(rename = "boolean")]
+            Boolean,
+            #[serde(rename = "number")]
+            Number,
+        }
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&Type::Boolean).unwrap(),
+            r#""boolean""#
+        );
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&Type::Number).unwrap(),
+            r#""number""#
+        );
+    }
+
+    #[test]
+    fn enum_newtype_variant() {
+        #[derive(Serialize)]
+        enum Reading {
+            Temperature(i8),
+            Label(&'static str),
+        }
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&Reading::Temperature(-5)).unwrap(),
+            r#"{"Temperature":-5}"#
+        );
+        assert_eq!(
+            &*super::to_string::<N, _>(&[Reading::Label("a"), Reading::Temperature(1)])
+                .unwrap(),
+            r#"[{"Label":"a"},{"Temperature":1}]"#
+        );
+    }
+
+    #[test]
+    fn enum_struct_variant() {
+        #[derive(Serialize)]
+        enum Msg {
+            Ping,
+            Move { x: i32, y: i32 },
+        }
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&Msg::Ping).unwrap(),
+            r#""Ping""#
+        );
+        assert_eq!(
+            &*super::to_string::<N, _>(&Msg::Move { x: 1, y: 2 }).unwrap(),
+            r#"{"Move":{"x":1,"y":2}}"#
+        );
+    }
+
+    #[test]
+    fn enum_tuple_variant() {
+        #[derive(Serialize)]
+        enum Color {
+            Rgb(u8, u8, u8),
+        }
+
+        #[derive(Serialize)]
+        struct Led {
+            color: Color,
+        }
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&Color::Rgb(255, 0, 128)).unwrap(),
+            r#"{"Rgb":[255,0,128]}"#
+        );
+        assert_eq!(
+            &*super::to_string::<N, _>(&Led {
+                color: Color::Rgb(0, 0, 0),
+            }).unwrap(),
+            r#"{"color":{"Rgb":[0,0,0]}}"#
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn error_display() {
+        use super::Error;
+
+        assert_eq!(
+            format!(
+                "{}",
+                Error::BufferFull {
+                    capacity: 8,
+                    needed: 10,
+                }
+            ),
+            "buffer is full: at least 10 bytes needed, capacity is 8"
+        );
+        assert_eq!(format!("{}", Error::Custom), "custom error");
+        assert_eq!(format!("{}", Error::InvalidFloat), "NaN or infinite float");
+        assert_eq!(
+            format!("{}", Error::InvalidJsonpCallback),
+            "JSONP callback is not a valid JavaScript identifier"
+        );
+        assert_eq!(
+            format!("{}", Error::InvalidPackedElement),
+            "packed array elements are not all of the same type"
+        );
+        assert_eq!(format!("{}", Error::KeyMustBeAString), "map key must be a string");
+        assert_eq!(
+            format!("{}", Error::WriteFailed),
+            "failed to write into the output sink"
+        );
+    }
+
+    #[test]
+    fn float() {
+        assert_eq!(&*super::to_string::<N, _>(&20.5).unwrap(), "20.5");
+        assert_eq!(&*super::to_string::<N, _>(&-0.1).unwrap(), "-0.1");
+        assert_eq!(&*super::to_string::<N, _>(&-0.0).unwrap(), "-0");
+        assert_eq!(&*super::to_string::<N, _>(&1e16).unwrap(), "1e16");
+        assert_eq!(&*super::to_string::<N, _>(&1e-7).unwrap(), "1e-7");
+        assert_eq!(
+            &*super::to_string::<N, _>(&f64::MIN_POSITIVE).unwrap(),
+            "2.2250738585072014e-308"
+        );
+        assert_eq!(
+            &*super::to_string::<N, _>(&f32::MAX).unwrap(),
+            "3.4028235e38"
+        );
+    }
+
+    #[test]
+    fn float_f32() {
+        // the same with and without `ryu`
+        for &(v, json) in &[
+            (1e12f32, "1000000000000"),
+            (1e13, "1e13"),
+            (-1.5e13, "-1.5e13"),
+            (1e-6, "0.000001"),
+            (1e-7, "1e-7"),
+            (0.1, "0.1"),
+        ] {
+            assert_eq!(&*super::to_string::<N, _>(&v).unwrap(), json);
+        }
+    }
+
+    #[cfg(feature = "ryu")]
+    #[test]
+    fn float_ryu() {
+        assert_eq!(&*super::to_string::<N, _>(&0.1f64).unwrap(), "0.1");
+        assert_eq!(&*super::to_string::<N, _>(&1e20f64).unwrap(), "1e20");
+        assert_eq!(
+            &*super::to_string::<N, _>(&f32::MIN_POSITIVE).unwrap(),
+            "1.1754944e-38"
+        );
+
+        // unlike `serde_json`, no trailing `.0`
+        assert_eq!(&*super::to_string::<N, _>(&20f64).unwrap(), "20");
+    }
+
+    #[test]
+    fn float_format() {
+        use super::{FloatFormat, SerializerConfig};
+
+        let to_string = |v: f64, float_format: FloatFormat| {
+            let config = SerializerConfig {
+                float_format,
+                ..SerializerConfig::default()
+            };
+
+            super::to_string_with_options::<N, _>(&v, config).unwrap()
+        };
+
+        assert_eq!(&*to_string(1234.5, FloatFormat::Auto), "1234.5");
+        assert_eq!(&*to_string(1234.5, FloatFormat::Decimal), "1234.5");
+        assert_eq!(&*to_string(1234.5, FloatFormat::Scientific), "1.2345e3");
+
+        assert_eq!(&*to_string(1e20, FloatFormat::Auto), "1e20");
+        assert_eq!(
+            &*to_string(1e20, FloatFormat::Decimal),
+            "100000000000000000000"
+        );
+        assert_eq!(&*to_string(1e20, FloatFormat::Scientific), "1e20");
+
+        assert_eq!(&*to_string(-1e-7, FloatFormat::Auto), "-1e-7");
+        assert_eq!(&*to_string(-1e-7, FloatFormat::Decimal), "-0.0000001");
+        assert_eq!(&*to_string(-1e-7, FloatFormat::Scientific), "-1e-7");
+
+        assert_eq!(&*to_string(0., FloatFormat::Scientific), "0e0");
+
+        // longer than any scratch buffer on the stack
+        let tiny = to_string(1.5e-60, FloatFormat::Decimal);
+        assert_eq!(tiny.len(), 63);
+        assert!(tiny.starts_with("0.000") && tiny.ends_with("00015"));
+    }
+
+    #[test]
+    fn float_format_serde_json() {
+        use super::{FloatFormat, SerializerConfig};
+
+        let config = SerializerConfig {
+            float_format: FloatFormat::SerdeJson,
+            ..SerializerConfig::default()
+        };
+
+        for &(v, json) in &[
+            (42.0, "42.0"),
+            (-0.0, "-0.0"),
+            (1e15, "1000000000000000.0"),
+            (1e16, "1e+16"),
+            (1e21, "1e+21"),
+            (1e-5, "0.00001"),
+            (1e-7, "1e-7"),
+            (0.1, "0.1"),
+            (-1234.5, "-1234.5"),
+            (f64::MAX, "1.7976931348623157e+308"),
+        ] {
+            assert_eq!(
+                &*super::to_string_with_options::<N, _>(&v, config).unwrap(),
+                json
+            );
+        }
+
+        for &(v, json) in &[
+            (42.0f32, "42.0"),
+            (1e12, "1000000000000.0"),
+            (1e13, "1e+13"),
+            (1e-6, "0.000001"),
+            (1e-7, "1e-7"),
+            (0.1, "0.1"),
+            (f32::MIN_POSITIVE, "1.1754944e-38"),
+        ] {
+            assert_eq!(
+                &*super::to_string_with_options::<N, _>(&v, config).unwrap(),
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn inject_version() {
+        use super::SerializerConfig;
+
+        #[derive(Serialize)]
+        struct Position {
+            x: i8,
+        }
+
+        #[derive(Serialize)]
+        struct Report {
+            id: u8,
+            position: Position,
+        }
+
+        let config = SerializerConfig {
+            inject_version: Some(("_v", 2)),
+            ..SerializerConfig::default()
+        };
+
+        let report = Report {
+            id: 1,
+            position: Position { x: -3 },
+        };
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&report, config).unwrap(),
+            r#"{"_v":2,"id":1,"position":{"x":-3}}"#
+        );
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&[Position { x: 1 }], config).unwrap(),
+            r#"[{"x":1}]"#
+        );
+    }
+
+    #[test]
+    fn jsonp_callback() {
+        use super::{Error, SerializerConfig};
+
+        #[derive(Serialize)]
+        struct Led {
+            led: bool,
+        }
+
+        let mut config = SerializerConfig {
+            jsonp_callback: Some("cb"),
+            ..SerializerConfig::default()
+        };
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&Led { led: true }, config).unwrap(),
+            r#"cb({"led":true});"#
+        );
+
+        for callback in &["", "1cb", "cb.update", "cb()"] {
+            config.jsonp_callback = Some(*callback);
+            match super::to_string_with_options::<N, _>(&Led { led: true }, config) {
+                Err(Error::InvalidJsonpCallback) => {}
+                _ => panic!("{:?} was accepted", callback),
+            }
+        }
+    }
+
+    #[test]
+    fn map() {
+        use serde::ser::{Serialize, SerializeMap, Serializer};
+
+        struct Map<'a, K: 'a>(&'a [(K, u8)]);
+
+        impl<'a, K> Serialize for Map<'a, K>
+        where
+            K: Serialize,
+        {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(Some(self.0.len()))?;
+                for (k, v) in self.0 {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+        }
+
+        let empty: &[(&str, u8)] = &[];
+        assert_eq!(&*super::to_string::<N, _>(&Map(empty)).unwrap(), "{}");
+        assert_eq!(
+            &*super::to_string::<N, _>(&Map(&[("a", 1)])).unwrap(),
+            r#"{"a":1}"#
+        );
+        assert_eq!(
+            &*super::to_string::<N, _>(&Map(&[("a", 1), ("b\"", 2), ("c", 3)])).unwrap(),
+            r#"{"a":1,"b\"":2,"c":3}"#
+        );
+
+        // integer keys are rejected ...
+        match super::to_string::<N, _>(&Map(&[(-1i8, 1), (2, 2)])) {
+            Err(super::Error::KeyMustBeAString) => {}
+            _ => panic!("an integer key was accepted"),
+        }
+
+        // ... unless they are quoted
+        let config = super::SerializerConfig {
+            quote_integer_keys: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&Map(&[(-1i8, 1), (2, 2)]), config).unwrap(),
+            r#"{"-1":1,"2":2}"#
+        );
+
+        // other keys are rejected either way
+        match super::to_string_with_options::<N, _>(&Map(&[(true, 1)]), config) {
+            Err(super::Error::KeyMustBeAString) => {}
+            _ => panic!("a bool key was accepted"),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn map_hash() {
+        use std::collections::HashMap;
+
+        let config = super::SerializerConfig {
+            quote_integer_keys: true,
+            ..Default::default()
+        };
+
+        // a single entry, as the iteration order is unspecified
+        let mut map = HashMap::new();
+        map.insert(1u32, true);
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&map, config).unwrap(),
+            r#"{"1":true}"#
+        );
+
+        map.insert(2, false);
+        let json = super::to_string_with_options::<N, _>(&map, config).unwrap();
+        assert!(&*json == r#"{"1":true,"2":false}"# || &*json == r#"{"2":false,"1":true}"#);
+        assert_eq!(::de::from_str::<HashMap<u32, bool>>(&json), Ok(map));
+    }
+
+    #[test]
+    fn map_len_hint() {
+        use serde::ser::{Serialize, SerializeMap, Serializer};
+
+        struct Map<'a> {
+            entries: &'a [(&'a str, u8)],
+            hint: Option<usize>,
+        }
+
+        impl<'a> Serialize for Map<'a> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(self.hint)?;
+                for &(k, v) in self.entries {
+                    map.serialize_entry(k, &v)?;
+                }
+                map.end()
+            }
+        }
+
+        for &entries in &[&[][..], &[("a", 1)], &[("a", 1), ("b", 2), ("c", 3)]] {
+            let known = Map {
+                entries,
+                hint: Some(entries.len()),
+            };
+            let unknown = Map {
+                entries,
+                hint: None,
+            };
+            assert_eq!(
+                super::to_string::<N, _>(&known).unwrap(),
+                super::to_string::<N, _>(&unknown).unwrap()
+            );
+        }
+
+        // a wrong hint doesn't break the output either
+        let wrong = Map {
+            entries: &[("a", 1), ("b", 2)],
+            hint: Some(1),
+        };
+        assert_eq!(
+            &*super::to_string::<N, _>(&wrong).unwrap(),
+            r#"{"a":1,"b":2}"#
+        );
+    }
+
+    #[test]
+    fn map_linear() {
+        use heapless::LinearMap;
+
+        let mut map = LinearMap::<&str, u8, 4>::new();
+        map.insert("zeta", 1).unwrap();
+        map.insert("alpha", 2).unwrap();
+        map.insert("mu", 3).unwrap();
+
+        // insertion order, not key order
+        assert_eq!(
+            &*super::to_string::<N, _>(&map).unwrap(),
+            r#"{"zeta":1,"alpha":2,"mu":3}"#
+        );
+
+        // overwriting a key keeps its position
+        map.insert("alpha", 4).unwrap();
+        assert_eq!(
+            &*super::to_string::<N, _>(&map).unwrap(),
+            r#"{"zeta":1,"alpha":4,"mu":3}"#
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn map_std() {
+        use std::collections::BTreeMap;
+
+        #[derive(Serialize)]
+        struct Readings {
+            readings: BTreeMap<u8, bool>,
+        }
+
+        let mut readings = Readings {
+            readings: BTreeMap::new(),
+        };
+        readings.readings.insert(2, false);
+        readings.readings.insert(1, true);
+
+        match super::to_string::<N, _>(&readings) {
+            Err(super::Error::KeyMustBeAString) => {}
+            _ => panic!("an integer key was accepted"),
+        }
+
+        let config = super::SerializerConfig {
+            quote_integer_keys: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&readings, config).unwrap(),
+            r#"{"readings":{"1":true,"2":false}}"#
+        );
+    }
+
+    #[test]
+    fn newtype_struct() {
+        #[derive(Serialize)]
+        struct Celsius(f32);
+
+        #[derive(Serialize)]
+        struct Meters(u16);
+
+        #[derive(Serialize)]
+        struct Reading {
+            id: Meters,
+            temperature: Celsius,
+        }
+
+        assert_eq!(&*super::to_string::<N, _>(&Celsius(12.5)).unwrap(), "12.5");
+        assert_eq!(
+            &*super::to_string::<N, _>(&Reading {
+                id: Meters(12),
+                temperature: Celsius(12.5),
+            }).unwrap(),
+            r#"{"id":12,"temperature":12.5}"#
+        );
+    }
+
+    #[test]
+    fn non_finite() {
+        use super::Error;
+
+        #[derive(Serialize)]
+        struct Temperature {
+            celsius: f32,
+        }
+
+        for v in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            match super::to_string::<N, _>(&Temperature { celsius: *v }) {
+                Err(Error::InvalidFloat) => {}
+                _ => panic!("expected `InvalidFloat`"),
+            }
+        }
+    }
+
+    #[test]
+    fn non_finite_as_literal() {
+        use super::{NonFinitePolicy, SerializerConfig};
+
+        let config = SerializerConfig {
+            non_finite: NonFinitePolicy::AsLiteral,
+            ..SerializerConfig::default()
+        };
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&f32::NAN, config).unwrap(),
+            "NaN"
+        );
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(
+                &[f64::INFINITY, 1.5, f64::NEG_INFINITY],
+                config
+            ).unwrap(),
+            "[Infinity,1.5,-Infinity]"
+        );
+    }
+
+    #[test]
+    fn non_finite_as_null() {
+        use super::{NonFinitePolicy, SerializerConfig};
+
+        let config = SerializerConfig {
+            non_finite: NonFinitePolicy::Null,
+            ..SerializerConfig::default()
+        };
+
+        let readings = [1.5, f32::NAN, -0.25, f32::INFINITY];
+        let json = super::to_string_with_options::<N, _>(&readings, config).unwrap();
+        assert_eq!(&*json, "[1.5,null,-0.25,null]");
+        assert_eq!(
+            ::de::from_str::<[Option<f32>; 4]>(&json),
+            Ok([Some(1.5), None, Some(-0.25), None])
+        );
+    }
+
+    #[test]
+    fn on_element_end() {
+        use core::fmt;
+
+        use super::{Hook, SerializerConfig};
+
+        fn marker(w: &mut dyn fmt::Write) -> fmt::Result {
+            w.write_str("|")
+        }
+
+        let config = SerializerConfig {
+            on_element_end: Some(Hook(marker)),
+            ..SerializerConfig::default()
+        };
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&[1, 2, 3], config).unwrap(),
+            "[1|,2|,3|]"
+        );
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&[[1, 2], [3, 4]], config).unwrap(),
+            "[[1|,2|]|,[3|,4|]|]"
+        );
+    }
+
+    #[test]
+    fn pack_arrays() {
+        use base64::Decoder;
+
+        use super::{Error, SerializerConfig};
+
+        fn to_string<T>(value: &T) -> super::Result<::heapless::String<N>>
+        where
+            T: ::serde::Serialize + ?Sized,
+        {
+            let config = SerializerConfig {
+                pack_arrays: true,
+                ..SerializerConfig::default()
+            };
+            super::to_string_with_options(value, config)
+        }
+
+        let mut pixels = [0u8; 16];
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = i as u8;
+        }
+
+        let json = to_string(&pixels).unwrap();
+        assert_eq!(&*json, r#""u8:AAECAwQFBgcICQoLDA0ODw==""#);
+        assert!(json.len() < super::to_string::<N, _>(&pixels).unwrap().len());
+
+        let mut decoder = Decoder::new(json[4..json.len() - 1].as_bytes());
+        for pixel in &pixels {
+            assert_eq!(decoder.next(), Ok(Some(*pixel)));
+        }
+        assert_eq!(decoder.next(), Ok(None));
+
+        assert_eq!(&*to_string(&[0x1234u16, 0xffff]).unwrap(), r#""u16:EjT//w==""#);
+        assert_eq!(&*to_string(&[[1u8, 2], [3, 4]]).unwrap(), r#"["u8:AQI=","u8:AwQ="]"#);
+
+        // not packed
+        assert_eq!(&*to_string(&[0u8; 0]).unwrap(), "[]");
+        assert_eq!(&*to_string(&[1u32, 2]).unwrap(), "[1,2]");
+        assert_eq!(&*to_string(&[Some(1u8), None]).unwrap(), "[1,null]");
+        assert_eq!(&*to_string(&("a", 1u8)).unwrap(), r#"["a",1]"#);
+
+        match to_string(&(1u8, 2u16)) {
+            Err(Error::InvalidPackedElement) => {}
+            _ => panic!("expected `InvalidPackedElement`"),
+        }
+    }
+
+    #[test]
+    fn pretty() {
+        #[derive(Serialize)]
+        enum Mode {
+            Blink { period: u32 },
+        }
+
+        #[derive(Serialize)]
+        struct Led {
+            led: bool,
+            rgb: [u8; 3],
+            tags: [u8; 0],
+        }
+
+        #[derive(Serialize)]
+        struct Config {
+            leds: [Led; 1],
+            mode: Mode,
+        }
+
+        assert_eq!(
+            &*super::to_string_pretty::<256, _>(&Config {
+                leds: [Led {
+                    led: true,
+                    rgb: [255, 0, 0],
+                    tags: [],
+                }],
+                mode: Mode::Blink { period: 500 },
+            }).unwrap(),
+            r#"{
+  "leds": [
+    {
+      "led": true,
+      "rgb": [
+        255,
+        0,
+        0
+      ],
+      "tags": []
+    }
+  ],
+  "mode": {
+    "Blink": {
+      "period": 500
+    }
+  }
+}"#
+        );
+
+        assert_eq!(&*super::to_string_pretty::<N, _>(&[0u8; 0]).unwrap(), "[]");
+        assert_eq!(&*super::to_string_pretty::<N, _>(&42).unwrap(), "42");
+    }
+
+    #[test]
+    fn pretty_empty() {
+        #[derive(Serialize)]
+        struct Empty {}
+
+        #[derive(Serialize)]
+        struct Log {
+            entries: [u8; 0],
+            meta: Empty,
+        }
+
+        let log = Log {
+            entries: [],
+            meta: Empty {},
+        };
+
+        let pretty = super::PrettyConfig { indent: "\t" };
+        assert_eq!(
+            &*super::to_string_pretty_with::<N, _>(&log, pretty).unwrap(),
+            "{\n\t\"entries\": [],\n\t\"meta\": {}\n}"
+        );
+        assert_eq!(
+            &*super::to_string_pretty_with::<N, _>(&Empty {}, pretty).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn pretty_indent() {
+        #[derive(Serialize)]
+        struct Led {
+            rgb: [u8; 2],
+        }
+
+        let led = Led { rgb: [1, 2] };
+
+        let pretty = super::PrettyConfig { indent: "\t" };
+        assert_eq!(
+            &*super::to_string_pretty_with::<N, _>(&led, pretty).unwrap(),
+            "{\n\t\"rgb\": [\n\t\t1,\n\t\t2\n\t]\n}"
+        );
+
+        let pretty = super::PrettyConfig { indent: "    " };
+        assert_eq!(
+            &*super::to_string_pretty_with::<N, _>(&led, pretty).unwrap(),
+            r#"{
+    "rgb": [
+        1,
+        2
+    ]
+}"#
+        );
+    }
+
+    #[test]
+    fn separators() {
+        use super::{Separators, SerializerConfig};
+
+        #[derive(Serialize)]
+        struct Pair {
+            a: u8,
+            b: [u8; 2],
+        }
+
+        let pair = Pair { a: 1, b: [2, 3] };
+
+        let mut config = SerializerConfig {
+            separators: Separators {
+                space_before_colon: true,
+                space_after_colon: true,
+                ..Separators::COMPACT
+            },
+            ..SerializerConfig::default()
+        };
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&pair, config).unwrap(),
+            r#"{"a" : 1,"b" : [2,3]}"#
+        );
+
+        config.separators = Separators {
+            space_before_comma: true,
+            ..Separators::SPACED
+        };
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&pair, config).unwrap(),
+            r#"{"a": 1 , "b": [2 , 3]}"#
+        );
+
+        // compact by default
+        assert_eq!(
+            &*super::to_string::<N, _>(&pair).unwrap(),
+            r#"{"a":1,"b":[2,3]}"#
+        );
+    }
+
+    #[test]
+    fn serializer() {
+        #[derive(Serialize)]
+        struct Led {
+            on: bool,
+        }
+
+        let mut serializer = super::Serializer::<N>::new();
+
+        serializer.serialize(&Led { on: true }).unwrap();
+        assert_eq!(serializer.as_str(), r#"{"on":true}"#);
+
+        serializer.clear();
+        serializer.serialize(&[1, 2]).unwrap();
+        assert_eq!(serializer.as_str(), "[1,2]");
+        assert_eq!(serializer.as_bytes(), b"[1,2]");
+
+        // a failed value leaves the previous output intact
+        match serializer.serialize(&[0u8; N][..]) {
+            Err(super::Error::BufferFull { .. }) => {}
+            _ => panic!("expected `BufferFull`"),
+        }
+        assert_eq!(serializer.as_str(), "[1,2]");
+    }
+
+    #[test]
+    fn significant_digits() {
+        use super::SerializerConfig;
+
+        let config = SerializerConfig {
+            significant_digits: Some(3),
+            ..SerializerConfig::default()
+        };
+
+        let to_string = |v: f64| super::to_string_with_options::<N, _>(&v, config).unwrap();
+
+        assert_eq!(&*to_string(12345.678), "1.23e4");
+        assert_eq!(&*to_string(123.456), "123");
+        assert_eq!(&*to_string(42.), "42");
+        assert_eq!(&*to_string(1.23456), "1.23");
+        assert_eq!(&*to_string(-0.5), "-0.5");
+        assert_eq!(&*to_string(9.996), "10");
+        assert_eq!(&*to_string(0.000123456), "0.000123");
+        assert_eq!(&*to_string(0.0000123456), "1.23e-5");
+        assert_eq!(&*to_string(1e300), "1e300");
+    }
+
+    #[test]
+    fn skip_empty_strings() {
+        use super::SerializerConfig;
+
+        #[derive(Serialize)]
+        struct User<'a> {
+            nick: &'a str,
+            name: &'a str,
+            id: u8,
+        }
+
+        let config = SerializerConfig {
+            skip_empty_strings: true,
+            ..SerializerConfig::default()
+        };
+
+        let user = User {
+            nick: "",
+            name: "ferris",
+            id: 1,
+        };
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&user, config).unwrap(),
+            r#"{"name":"ferris","id":1}"#
+        );
+
+        let user = User {
+            nick: "crab",
+            name: "",
+            id: 1,
+        };
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&user, config).unwrap(),
+            r#"{"nick":"crab","id":1}"#
+        );
+
+        // elements of arrays are kept
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&["", "a"], config).unwrap(),
+            r#"["","a"]"#
+        );
+
+        // off by default
+        assert_eq!(
+            &*super::to_string::<N, _>(&user).unwrap(),
+            r#"{"nick":"crab","name":"","id":1}"#
+        );
+    }
+
+    #[test]
+    fn space_after_comma() {
+        use super::{Separators, SerializerConfig};
+
+        let config = SerializerConfig {
+            separators: Separators::SPACED,
+            ..SerializerConfig::default()
+        };
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&[1, 2, 3], config).unwrap(),
+            "[1, 2, 3]"
+        );
+
+        #[derive(Serialize)]
+        struct Pair {
+            a: u8,
+            b: u8,
+        }
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&Pair { a: 1, b: 2 }, config).unwrap(),
+            r#"{"a": 1, "b": 2}"#
+        );
+    }
+
+    #[test]
+    fn str() {
+        assert_eq!(
+            &*super::to_string::<N, _>("hello").unwrap(),
+            r#""hello""#
+        );
+    }
 
-impl ser::SerializeStructVariant for Unreachable {
-    type Ok = ();
-    type Error = Error;
+    #[test]
+    fn str_control_characters() {
+        let mut bytes = [0u8; 0x20];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let s = ::core::str::from_utf8(&bytes).unwrap();
 
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, _value: &T) -> Result<()>
-    where
-        T: ser::Serialize,
-    {
-        unreachable!()
-    }
+        let json = super::to_string::<256, _>(s).unwrap();
+        assert_eq!(
+            &*json,
+            concat!(
+                r#""\u0000\u0001\u0002\u0003\u0004\u0005\u0006\u0007\b\t\n\u000B\f\r\u000E\u000F"#,
+                r#"\u0010\u0011\u0012\u0013\u0014\u0015\u0016\u0017"#,
+                r#"\u0018\u0019\u001A\u001B\u001C\u001D\u001E\u001F""#,
+            )
+        );
 
-    fn end(self) -> Result<Self::Ok> {
-        unreachable!()
+        let value: ::serde_json::Value = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(value.as_str(), Some(s));
     }
-}
-
-#[cfg(test)]
-mod tests {
-    const N: usize = 128;
 
     #[test]
-    fn array() {
+    fn str_escape_all_control() {
+        use super::{ControlEscapes, SerializerConfig};
+
+        let to_string = |escapes| {
+            let config = SerializerConfig {
+                escape_all_control: escapes,
+                ..SerializerConfig::default()
+            };
+            super::to_string_with_options::<N, _>("a\tb\x7f", config).unwrap()
+        };
+
+        assert_eq!(&*to_string(None), "\"a\\tb\x7f\"");
         assert_eq!(
-            &*super::to_string::<[u8; N], _>(&[0, 1, 2]).unwrap(),
-            "[0,1,2]"
+            &*to_string(Some(ControlEscapes::default())),
+            r#""a\tb\u007F""#
+        );
+        assert_eq!(
+            &*to_string(Some(ControlEscapes {
+                use_short_escapes: false,
+            })),
+            r#""a\u0009b\u007F""#
         );
     }
 
     #[test]
-    fn bool() {
-        assert_eq!(&*super::to_string::<[u8; N], _>(&true).unwrap(), "true");
+    fn str_escaped() {
+        for &(s, expected) in &[
+            (r#"he said "hi""#, r#""he said \"hi\"""#),
+            (r"C:\temp", r#""C:\\temp""#),
+            (r"\", r#""\\""#),
+            (r#"\"\\""#, r#""\\\"\\\\\"""#),
+            (r#"""#, r#""\"""#),
+        ] {
+            let json = super::to_string::<N, _>(s).unwrap();
+            assert_eq!(&*json, expected);
+            let value: ::serde_json::Value = ::serde_json::from_str(&json).unwrap();
+            assert_eq!(value.as_str(), Some(s));
+        }
+
+        assert_eq!(&*super::to_string::<N, _>(&'"').unwrap(), r#""\"""#);
+        assert_eq!(&*super::to_string::<N, _>(&'\\').unwrap(), r#""\\""#);
     }
 
     #[test]
-    fn enum_() {
+    fn struct_bool() {
         #[derive(Serialize)]
-        enum Type {
-            #[serde(rename = "boolean")]
-            Boolean,
-            #[serde(rename = "number")]
-            Number,
+        struct Led {
+            led: bool,
         }
 
         assert_eq!(
-            &*super::to_string::<[u8; N], _>(&Type::Boolean).unwrap(),
-            r#""boolean""#
-        );
-
-        assert_eq!(
-            &*super::to_string::<[u8; N], _>(&Type::Number).unwrap(),
-            r#""number""#
+            &*super::to_string::<N, _>(&Led { led: true }).unwrap(),
+            r#"{"led":true}"#
         );
     }
 
     #[test]
-    fn str() {
+    fn struct_f32() {
+        #[derive(Serialize)]
+        struct Temperature {
+            temperature: f32,
+        }
+
         assert_eq!(
-            &*super::to_string::<[u8; N], _>("hello").unwrap(),
-            r#""hello""#
+            &*super::to_string::<N, _>(&Temperature { temperature: -20.5 }).unwrap(),
+            r#"{"temperature":-20.5}"#
         );
     }
 
     #[test]
-    fn struct_bool() {
+    fn struct_f64() {
         #[derive(Serialize)]
-        struct Led {
-            led: bool,
+        struct Temperature {
+            temperature: f64,
         }
 
         assert_eq!(
-            &*super::to_string::<[u8; N], _>(&Led { led: true }).unwrap(),
-            r#"{"led":true}"#
+            &*super::to_string::<N, _>(&Temperature { temperature: 36.6 }).unwrap(),
+            r#"{"temperature":36.6}"#
         );
     }
 
@@ -479,26 +2917,99 @@ mod tests {
         }
 
         assert_eq!(
-            &*super::to_string::<[u8; N], _>(&Temperature { temperature: 127 }).unwrap(),
+            &*super::to_string::<N, _>(&Temperature { temperature: 127 }).unwrap(),
             r#"{"temperature":127}"#
         );
 
         assert_eq!(
-            &*super::to_string::<[u8; N], _>(&Temperature { temperature: 20 }).unwrap(),
+            &*super::to_string::<N, _>(&Temperature { temperature: 20 }).unwrap(),
             r#"{"temperature":20}"#
         );
 
         assert_eq!(
-            &*super::to_string::<[u8; N], _>(&Temperature { temperature: -17 }).unwrap(),
+            &*super::to_string::<N, _>(&Temperature { temperature: -17 }).unwrap(),
             r#"{"temperature":-17}"#
         );
 
         assert_eq!(
-            &*super::to_string::<[u8; N], _>(&Temperature { temperature: -128 }).unwrap(),
+            &*super::to_string::<N, _>(&Temperature { temperature: -128 }).unwrap(),
             r#"{"temperature":-128}"#
         );
     }
 
+    // NOTE serde only derives `Serialize` for structs with flattened fields with `std` or `alloc`
+    #[cfg(feature = "std")]
+    #[test]
+    fn struct_flatten() {
+        #[derive(Serialize)]
+        struct Position {
+            x: i8,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            y: Option<i8>,
+        }
+
+        #[derive(Serialize)]
+        struct Report {
+            id: u8,
+            #[serde(flatten)]
+            position: Position,
+            led: bool,
+        }
+
+        #[derive(Serialize)]
+        struct Empty {}
+
+        #[derive(Serialize)]
+        struct Wrapper {
+            #[serde(flatten)]
+            empty: Empty,
+            #[serde(flatten)]
+            report: Report,
+        }
+
+        let report = Report {
+            id: 7,
+            position: Position { x: 1, y: Some(-2) },
+            led: true,
+        };
+        assert_eq!(
+            &*super::to_string::<N, _>(&report).unwrap(),
+            r#"{"id":7,"x":1,"y":-2,"led":true}"#
+        );
+
+        let report = Report {
+            id: 7,
+            position: Position { x: 1, y: None },
+            led: true,
+        };
+        assert_eq!(
+            &*super::to_string::<N, _>(&report).unwrap(),
+            r#"{"id":7,"x":1,"led":true}"#
+        );
+
+        // nothing is written for an empty flattened struct, not even a comma
+        let wrapper = Wrapper {
+            empty: Empty {},
+            report,
+        };
+        assert_eq!(
+            &*super::to_string::<N, _>(&wrapper).unwrap(),
+            r#"{"id":7,"x":1,"led":true}"#
+        );
+
+        // only structs and maps can be flattened
+        #[derive(Serialize)]
+        struct Config {
+            #[serde(flatten)]
+            mode: u8,
+        }
+
+        match super::to_string::<N, _>(&Config { mode: 1 }) {
+            Err(super::Error::Custom) => {}
+            _ => panic!("expected `Custom`"),
+        }
+    }
+
     #[test]
     fn struct_option() {
         #[derive(Serialize)]
@@ -507,19 +3018,79 @@ mod tests {
         }
 
         assert_eq!(
-            super::to_string::<[u8; N], _>(&Property {
+            super::to_string::<N, _>(&Property {
                 description: Some("An ambient temperature sensor"),
             }).unwrap(),
             r#"{"description":"An ambient temperature sensor"}"#
         );
 
-        // XXX Ideally this should produce "{}"
+        // use `skip_serializing_if` to omit the field instead, see `struct_skip_none`
         assert_eq!(
-            super::to_string::<[u8; N], _>(&Property { description: None }).unwrap(),
+            super::to_string::<N, _>(&Property { description: None }).unwrap(),
             r#"{"description":null}"#
         );
     }
 
+    #[test]
+    fn struct_skip_none() {
+        #[derive(Serialize)]
+        struct Payload<'a> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            name: Option<&'a str>,
+        }
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&Payload { name: None }).unwrap(),
+            r#"{}"#
+        );
+        assert_eq!(
+            &*super::to_string::<N, _>(&Payload { name: Some("x") }).unwrap(),
+            r#"{"name":"x"}"#
+        );
+
+        // a skipped first field doesn't leave a dangling comma behind
+        #[derive(Serialize)]
+        struct Reading<'a> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            name: Option<&'a str>,
+            value: u8,
+        }
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&Reading {
+                name: None,
+                value: 7,
+            }).unwrap(),
+            r#"{"value":7}"#
+        );
+
+        // nor does a skipped field in the middle
+        #[derive(Serialize)]
+        struct Sensor<'a> {
+            id: u8,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            location: Option<&'a str>,
+            value: u8,
+        }
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&Sensor {
+                id: 1,
+                location: None,
+                value: 7,
+            }).unwrap(),
+            r#"{"id":1,"value":7}"#
+        );
+        assert_eq!(
+            &*super::to_string::<N, _>(&Sensor {
+                id: 1,
+                location: Some("attic"),
+                value: 7,
+            }).unwrap(),
+            r#"{"id":1,"location":"attic","value":7}"#
+        );
+    }
+
     #[test]
     fn struct_u8() {
         #[derive(Serialize)]
@@ -528,7 +3099,7 @@ mod tests {
         }
 
         assert_eq!(
-            &*super::to_string::<[u8; N], _>(&Temperature { temperature: 20 }).unwrap(),
+            &*super::to_string::<N, _>(&Temperature { temperature: 20 }).unwrap(),
             r#"{"temperature":20}"#
         );
     }
@@ -539,7 +3110,7 @@ mod tests {
         struct Empty {}
 
         assert_eq!(
-            &*super::to_string::<[u8; N], _>(&Empty {}).unwrap(),
+            &*super::to_string::<N, _>(&Empty {}).unwrap(),
             r#"{}"#
         );
 
@@ -550,8 +3121,366 @@ mod tests {
         }
 
         assert_eq!(
-            &*super::to_string::<[u8; N], _>(&Tuple { a: true, b: false }).unwrap(),
+            &*super::to_string::<N, _>(&Tuple { a: true, b: false }).unwrap(),
             r#"{"a":true,"b":false}"#
         );
+
+        #[derive(Serialize)]
+        struct Renamed {
+            #[serde(rename = "a\"b")]
+            a: u8,
+            #[serde(rename = "c\\d\n")]
+            c: u8,
+        }
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&Renamed { a: 1, c: 2 }).unwrap(),
+            r#"{"a\"b":1,"c\\d\n":2}"#
+        );
+    }
+
+    #[cfg(feature = "embedded-hal")]
+    #[test]
+    fn to_serial() {
+        use embedded_hal::blocking::serial::Write;
+        use heapless::Vec;
+
+        use super::Error;
+
+        /// Serial port that records what it's sent, up to its capacity
+        struct Uart {
+            sent: Vec<u8, 16>,
+            flushed: bool,
+        }
+
+        impl Write<u8> for Uart {
+            type Error = ();
+
+            fn bwrite_all(&mut self, buffer: &[u8]) -> Result<(), ()> {
+                self.flushed = false;
+                self.sent.extend_from_slice(buffer)
+            }
+
+            fn bflush(&mut self) -> Result<(), ()> {
+                self.flushed = true;
+                Ok(())
+            }
+        }
+
+        let mut uart = Uart {
+            sent: Vec::new(),
+            flushed: false,
+        };
+        super::to_serial(&mut uart, &[1, 2, 3]).unwrap();
+        assert_eq!(&uart.sent[..], b"[1,2,3]");
+        assert!(uart.flushed);
+
+        let mut uart = Uart {
+            sent: Vec::new(),
+            flushed: false,
+        };
+        match super::to_serial(&mut uart, &[12345678u32, 12345678, 12345678]) {
+            Err(Error::WriteFailed) => {}
+            _ => panic!("expected `WriteFailed`"),
+        }
+    }
+
+    #[test]
+    fn to_slice() {
+        use super::Error;
+
+        #[derive(Serialize)]
+        struct Led {
+            led: bool,
+        }
+
+        let json = br#"{"led":true}"#;
+
+        let mut buf = [0; 12];
+        assert_eq!(super::to_slice(&Led { led: true }, &mut buf).unwrap(), json.len());
+        assert_eq!(&buf, json);
+
+        let mut buf = [0; 11];
+        match super::to_slice(&Led { led: true }, &mut buf) {
+            Err(Error::BufferFull {
+                capacity: 11,
+                needed: 12,
+            }) => {}
+            _ => panic!("expected `BufferFull`"),
+        }
+
+        // a plain array on the stack, no `heapless` involved
+        let mut buf: [u8; 32] = [0; 32];
+        let len = super::to_slice(&Led { led: true }, &mut buf).unwrap();
+        assert_eq!(&buf[..len], json);
+        assert!(buf[len..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn to_slices() {
+        use super::Error;
+
+        #[derive(Serialize)]
+        struct Led<'a> {
+            label: &'a str,
+            led: bool,
+        }
+
+        let led = Led {
+            label: "d\u{e9}sk",
+            led: true,
+        };
+        let json = r#"{"label":"désk","led":true}"#.as_bytes();
+
+        // the split falls in the middle of the `é`
+        let (mut head, mut tail) = ([0; 12], [0; 32]);
+        let counts = super::to_slices(&led, &mut [&mut head[..], &mut tail[..]]).unwrap();
+        assert_eq!(counts, [12, json.len() - 12]);
+
+        let mut joined = [0; 44];
+        joined[..12].copy_from_slice(&head);
+        joined[12..json.len()].copy_from_slice(&tail[..counts[1]]);
+        assert_eq!(&joined[..json.len()], json);
+
+        // unused segments are left empty
+        let led = Led { label: "", led: true };
+        let (mut a, mut b, mut c) = ([0; 16], [0; 16], [0; 16]);
+        let counts = super::to_slices(&led, &mut [&mut a[..], &mut b[..], &mut c[..]]);
+        assert_eq!(counts.unwrap(), [16, 7, 0]);
+
+        let (mut head, mut tail) = ([0; 8], [0; 8]);
+        match super::to_slices(&led, &mut [&mut head[..], &mut tail[..]]) {
+            Err(Error::BufferFull { capacity: 16, .. }) => {}
+            _ => panic!("expected `BufferFull`"),
+        }
+    }
+
+    #[test]
+    fn to_string_exact() {
+        use super::Error;
+
+        let value = [1u8, 20, 255];
+        let json = "[1,20,255]";
+
+        assert_eq!(&*super::to_string_exact::<10, _>(&value).unwrap(), json);
+
+        match super::to_string_exact::<9, _>(&value) {
+            Err(Error::BufferFull {
+                capacity: 9,
+                needed: 10,
+            }) => {}
+            _ => panic!("expected `BufferFull`"),
+        }
+
+        // the lower bound `to_string` reports is smaller
+        match super::to_string::<4, _>(&value) {
+            Err(Error::BufferFull { needed: 5, .. }) => {}
+            _ => panic!("expected `BufferFull`"),
+        }
+        match super::to_string_exact::<4, _>(&value) {
+            Err(Error::BufferFull { needed: 10, .. }) => {}
+            _ => panic!("expected `BufferFull`"),
+        }
+    }
+
+    #[test]
+    fn to_vec_canonical() {
+        use heapless::LinearMap;
+
+        type Map = LinearMap<&'static str, LinearMap<&'static str, u8, 2>, 4>;
+
+        // the same entries, inserted in different orders
+        let mut a = Map::new();
+        let mut inner = LinearMap::new();
+        inner.insert("y", 1).unwrap();
+        inner.insert("x", 2).unwrap();
+        a.insert("b\"", LinearMap::new()).unwrap();
+        a.insert("a!", inner).unwrap();
+        a.insert("a", LinearMap::new()).unwrap();
+
+        let mut b = Map::new();
+        let mut inner = LinearMap::new();
+        inner.insert("x", 2).unwrap();
+        inner.insert("y", 1).unwrap();
+        b.insert("a", LinearMap::new()).unwrap();
+        b.insert("a!", inner).unwrap();
+        b.insert("b\"", LinearMap::new()).unwrap();
+
+        let json = r#"{"a":{},"a!":{"x":2,"y":1},"b\"":{}}"#;
+        assert_eq!(&super::to_vec_canonical::<N, _>(&a).unwrap()[..], json.as_bytes());
+        assert_eq!(&super::to_vec_canonical::<N, _>(&b).unwrap()[..], json.as_bytes());
+
+        // insertion order otherwise
+        assert_eq!(
+            &*super::to_string::<N, _>(&a).unwrap(),
+            r#"{"b\"":{},"a!":{"y":1,"x":2},"a":{}}"#
+        );
+    }
+
+    #[test]
+    fn to_writer() {
+        use super::Error;
+
+        #[derive(Serialize)]
+        struct Led<'a> {
+            label: &'a str,
+            led: bool,
+        }
+
+        let led = Led {
+            label: "\u{1f4a1}\n",
+            led: true,
+        };
+        let json = "{\"label\":\"\u{1f4a1}\\n\",\"led\":true}";
+
+        let mut s: ::heapless::String<N> = ::heapless::String::new();
+        super::to_writer(&mut s, &led).unwrap();
+        assert_eq!(&*s, json);
+
+        let mut s: ::heapless::String<8> = ::heapless::String::new();
+        match super::to_writer(&mut s, &led) {
+            Err(Error::WriteFailed) => {}
+            _ => panic!("expected `WriteFailed`"),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn to_writer_std() {
+        let mut s = ::std::string::String::new();
+        super::to_writer(&mut s, &[1.5, -2.]).unwrap();
+        assert_eq!(s, "[1.5,-2]");
+    }
+
+    #[test]
+    fn truncate_depth() {
+        use super::SerializerConfig;
+
+        #[derive(Serialize)]
+        struct Inner {
+            x: u8,
+        }
+
+        #[derive(Serialize)]
+        struct Middle {
+            inner: Inner,
+            samples: [u8; 2],
+            y: u8,
+        }
+
+        #[derive(Serialize)]
+        struct Outer {
+            middle: Middle,
+            z: u8,
+        }
+
+        let outer = Outer {
+            middle: Middle {
+                inner: Inner { x: 1 },
+                samples: [2, 3],
+                y: 4,
+            },
+            z: 5,
+        };
+
+        let mut config = SerializerConfig {
+            truncate_depth: Some(2),
+            ..SerializerConfig::default()
+        };
+
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&outer, config).unwrap(),
+            r#"{"middle":{"inner":"...","samples":"...","y":4},"z":5}"#
+        );
+
+        config.truncate_depth = Some(0);
+        config.truncate_placeholder = Some("<truncated>");
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&outer, config).unwrap(),
+            r#""<truncated>""#
+        );
+
+        // deep enough
+        config.truncate_depth = Some(3);
+        assert_eq!(
+            &*super::to_string_with_options::<N, _>(&outer, config).unwrap(),
+            r#"{"middle":{"inner":{"x":1},"samples":[2,3],"y":4},"z":5}"#
+        );
+    }
+
+    #[test]
+    fn tuple() {
+        assert_eq!(&*super::to_string::<N, _>(&(1,)).unwrap(), "[1]");
+        assert_eq!(
+            &*super::to_string::<N, _>(&(1, "two", true)).unwrap(),
+            r#"[1,"two",true]"#
+        );
+        assert_eq!(
+            &*super::to_string::<N, _>(&((1, 2), (3, 4))).unwrap(),
+            "[[1,2],[3,4]]"
+        );
+        assert_eq!(
+            &*super::to_string::<N, _>(&(Some(-1.5), (), "", ('a',))).unwrap(),
+            r#"[-1.5,null,"",["a"]]"#
+        );
+    }
+
+    #[test]
+    fn tuple_struct() {
+        #[derive(Serialize)]
+        struct Point(i32, i32);
+
+        #[derive(Serialize)]
+        struct Rgb(u8, u8, u8);
+
+        assert_eq!(&*super::to_string::<N, _>(&Point(1, 2)).unwrap(), "[1,2]");
+        assert_eq!(
+            &*super::to_string::<N, _>(&[Rgb(255, 0, 0), Rgb(0, 128, 255)]).unwrap(),
+            "[[255,0,0],[0,128,255]]"
+        );
+    }
+
+    #[test]
+    fn unit() {
+        #[derive(Serialize)]
+        struct Ping;
+
+        assert_eq!(&*super::to_string::<N, _>(&()).unwrap(), "null");
+        assert_eq!(&*super::to_string::<N, _>(&Ping).unwrap(), "null");
+    }
+
+    #[test]
+    fn wrappers() {
+        use core::cell::RefCell;
+
+        #[derive(Serialize)]
+        struct Counter<'a> {
+            count: RefCell<u8>,
+            total: &'a mut u8,
+        }
+
+        let mut total = 7;
+        let counter = Counter {
+            count: RefCell::new(3),
+            total: &mut total,
+        };
+
+        assert_eq!(
+            &*super::to_string::<N, _>(&counter).unwrap(),
+            r#"{"count":3,"total":7}"#
+        );
+
+        let mut n = 42u8;
+        assert_eq!(&*super::to_string::<N, _>(&&mut n).unwrap(), "42");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn wrappers_std() {
+        use std::sync::Mutex;
+
+        // the mutex is locked while it's being serialized
+        let count = Mutex::new(3u8);
+        assert_eq!(&*super::to_string::<N, _>(&count).unwrap(), "3");
     }
 }
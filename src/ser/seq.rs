@@ -1,62 +1,261 @@
-use core::marker::Unsize;
-
 use serde::ser;
 
-use ser::{Error, Result, Serializer};
+use base64::Encoder;
+use ser::{Buffer, Emitter, Error, Result};
 
 pub struct SerializeSeq<'a, B>
 where
-    B: Unsize<[u8]> + 'a,
+    B: Buffer + 'a,
 {
-    de: &'a mut Serializer<B>,
+    de: &'a mut Emitter<B>,
+    encoder: Encoder,
     first: bool,
+    pack: Pack,
+    /// The sequence was serialized as `null` or as the truncation placeholder; its elements are
+    /// discarded
+    skip: bool,
+    /// Buffer position of the opening `[`
+    start: usize,
+    /// The sequence is the content of a tuple variant; `end` also closes the enclosing object
+    variant: bool,
+}
+
+/// Packing state of a sequence, see `SerializerConfig::pack_arrays`
+#[derive(Clone, Copy, PartialEq)]
+enum Pack {
+    /// Regular array
+    Off,
+    /// No element has been serialized yet; the first one decides
+    Probe,
+    U8,
+    U16,
+}
+
+/// Element captured by `Serializer::serialize_u8` / `serialize_u16` instead of being written
+pub(crate) enum Packed {
+    U8(u8),
+    U16(u16),
 }
 
 impl<'a, B> SerializeSeq<'a, B>
 where
-    B: Unsize<[u8]>,
+    B: Buffer,
 {
-    pub(crate) fn new(de: &'a mut Serializer<B>) -> Self {
-        SerializeSeq { de, first: true }
+    pub(crate) fn new(de: &'a mut Emitter<B>, start: usize) -> Self {
+        let pack = if de.config.pack_arrays {
+            Pack::Probe
+        } else {
+            Pack::Off
+        };
+
+        SerializeSeq {
+            de,
+            encoder: Encoder::new(),
+            first: true,
+            pack,
+            skip: false,
+            start,
+            variant: false,
+        }
+    }
+
+    pub(crate) fn skip(de: &'a mut Emitter<B>) -> Self {
+        SerializeSeq {
+            de,
+            encoder: Encoder::new(),
+            first: true,
+            pack: Pack::Off,
+            skip: true,
+            start: 0,
+            variant: false,
+        }
+    }
+
+    pub(crate) fn variant(mut self) -> Self {
+        self.variant = true;
+        self
+    }
+
+    fn encode(&mut self, bytes: &[u8]) -> Result<()> {
+        for byte in bytes {
+            if let Some(chars) = self.encoder.push(*byte) {
+                self.de.buf.extend_from_slice(&chars)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the opening `[` with the opening quote and the `header` of a packed array
+    fn start_packing(&mut self, pack: Pack, header: &[u8]) -> Result<()> {
+        self.de.buf.truncate(self.start);
+        self.de.buf.extend_from_slice(header)?;
+        self.pack = pack;
+        Ok(())
     }
 }
 
 impl<'a, B> ser::SerializeSeq for SerializeSeq<'a, B>
 where
-    B: Unsize<[u8]>,
+    B: Buffer,
 {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: ser::Serialize,
+        T: ser::Serialize + ?Sized,
     {
-        if !self.first {
-            self.de.buf.push(b',')?;
+        if self.skip {
+            return Ok(());
         }
-        self.first = false;
 
-        value.serialize(&mut *self.de)?;
-        Ok(())
+        if self.pack == Pack::Off {
+            if self.first {
+                self.de.newline()?;
+            } else {
+                self.de.comma()?;
+            }
+            self.first = false;
+
+            value.serialize(&mut *self.de)?;
+            return self.de.element_end();
+        }
+
+        if self.pack == Pack::Probe {
+            // discarded if the array gets packed
+            self.de.newline()?;
+        }
+
+        let slot = self.de.pack_slot.replace(self.de.buf.len());
+        let ret = value.serialize(&mut *self.de);
+        self.de.pack_slot = slot;
+        ret?;
+
+        match (self.pack, self.de.packed.take()) {
+            (Pack::Probe, None) => {
+                // the first element was written as usual
+                self.pack = Pack::Off;
+                self.first = false;
+                self.de.element_end()
+            }
+            (Pack::Probe, Some(Packed::U8(v))) => {
+                self.start_packing(Pack::U8, b"\"u8:")?;
+                self.encode(&[v])
+            }
+            (Pack::Probe, Some(Packed::U16(v))) => {
+                self.start_packing(Pack::U16, b"\"u16:")?;
+                self.encode(&[(v >> 8) as u8, v as u8])
+            }
+            (Pack::U8, Some(Packed::U8(v))) => self.encode(&[v]),
+            (Pack::U16, Some(Packed::U16(v))) => self.encode(&[(v >> 8) as u8, v as u8]),
+            _ => Err(Error::InvalidPackedElement),
+        }
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.de.buf.push(b']')?;
+        if !self.skip {
+            self.de.depth -= 1;
+
+            match self.pack {
+                Pack::U8 | Pack::U16 => {
+                    if let Some(chars) = self.encoder.finish() {
+                        self.de.buf.extend_from_slice(&chars)?;
+                    }
+                    self.de.buf.push(b'"')?;
+                }
+                Pack::Off | Pack::Probe => {
+                    if !self.first {
+                        self.de.newline()?;
+                    }
+                    self.de.buf.push(b']')?;
+                }
+            }
+        }
+
+        if self.variant {
+            self.de.close_variant()?;
+        }
         Ok(())
     }
 }
 
+/// Handle passed to the closure of `serialize_array_with`
+pub struct SeqWriter<'a, B>
+where
+    B: Buffer + 'a,
+{
+    seq: SerializeSeq<'a, B>,
+}
+
+impl<'a, B> SeqWriter<'a, B>
+where
+    B: Buffer,
+{
+    pub(crate) fn new(seq: SerializeSeq<'a, B>) -> Self {
+        SeqWriter { seq }
+    }
+
+    pub(crate) fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self.seq)
+    }
+
+    /// Appends `value` to the array
+    pub fn element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(&mut self.seq, value)
+    }
+}
+
 impl<'a, B> ser::SerializeTuple for SerializeSeq<'a, B>
 where
-    B: Unsize<[u8]>,
+    B: Buffer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, B> ser::SerializeTupleStruct for SerializeSeq<'a, B>
+where
+    B: Buffer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, B> ser::SerializeTupleVariant for SerializeSeq<'a, B>
+where
+    B: Buffer,
 {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
     where
-        T: ser::Serialize,
+        T: ser::Serialize + ?Sized,
     {
         ser::SerializeSeq::serialize_element(self, value)
     }
@@ -1,54 +1,140 @@
-use core::marker::Unsize;
-
 use serde::ser;
 
-use ser::{Error, Result, Serializer};
+use ser::{crc32, Buffer, Emitter, Error, Result};
 
 pub struct SerializeStruct<'a, B>
 where
-    B: Unsize<[u8]> + 'a,
+    B: Buffer + 'a,
 {
-    de: &'a mut Serializer<B>,
+    de: &'a mut Emitter<B>,
     first: bool,
+    /// Buffer position of the opening `{`
+    start: usize,
+    /// The struct was serialized as the truncation placeholder; its fields are discarded
+    skip: bool,
+    /// The struct is the content of a struct variant; `end` also closes the enclosing object
+    variant: bool,
 }
 
 impl<'a, B> SerializeStruct<'a, B>
 where
-    B: Unsize<[u8]>,
+    B: Buffer,
 {
-    pub(crate) fn new(de: &'a mut Serializer<B>) -> Self {
-        SerializeStruct { de, first: true }
+    pub(crate) fn new(de: &'a mut Emitter<B>, start: usize) -> Self {
+        SerializeStruct {
+            de,
+            first: true,
+            start,
+            skip: false,
+            variant: false,
+        }
+    }
+
+    pub(crate) fn skip(de: &'a mut Emitter<B>) -> Self {
+        SerializeStruct {
+            de,
+            first: true,
+            start: 0,
+            skip: true,
+            variant: false,
+        }
+    }
+
+    pub(crate) fn variant(mut self) -> Self {
+        self.variant = true;
+        self
     }
 }
 
 impl<'a, B> ser::SerializeStruct for SerializeStruct<'a, B>
 where
-    B: Unsize<[u8]>,
+    B: Buffer,
 {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
-        T: ser::Serialize,
+        T: ser::Serialize + ?Sized,
     {
-        // XXX if `value` is `None` we not produce any output for this field
-        if !self.first {
-            self.de.buf.push(b',')?;
+        if self.skip {
+            return Ok(());
+        }
+
+        let field_start = self.de.buf.len();
+        let first = self.first;
+
+        if self.first {
+            self.de.newline()?;
+        } else {
+            self.de.comma()?;
         }
         self.first = false;
 
-        self.de.buf.push(b'"')?;
-        self.de.buf.extend_from_slice(key.as_bytes())?;
-        self.de.buf.extend_from_slice(b"\":")?;
+        self.de.key(key)?;
+        self.de.colon()?;
 
+        let value_start = self.de.buf.len();
         value.serialize(&mut *self.de)?;
 
+        if self.de.config.skip_empty_strings
+            && self.de.buf.as_slice()[value_start..] == *b"\"\""
+        {
+            // drop the whole field, including the comma in front of it
+            self.de.buf.truncate(field_start);
+            self.first = first;
+        }
+
         Ok(())
     }
 
-    fn end(self) -> Result<Self::Ok> {
-        self.de.buf.push(b'}')?;
+    /// Called instead of `serialize_field` for fields skipped by `skip_serializing_if`
+    ///
+    /// Nothing is written; the comma goes in front of the next field that's actually serialized.
+    fn skip_field(&mut self, _key: &'static str) -> Result<()> {
         Ok(())
     }
+
+    fn end(mut self) -> Result<Self::Ok> {
+        if !self.skip {
+            if let (1, Some(key)) = (self.de.depth, self.de.config.crc_field) {
+                let crc = crc32(&self.de.buf.as_slice()[self.start..]);
+                ser::SerializeStruct::serialize_field(&mut self, key, &crc)?;
+            }
+
+            self.de.depth -= 1;
+            if !self.first {
+                self.de.newline()?;
+            }
+            self.de.buf.push(b'}')?;
+        }
+
+        if self.variant {
+            self.de.close_variant()?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, B> ser::SerializeStructVariant for SerializeStruct<'a, B>
+where
+    B: Buffer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<()> {
+        ser::SerializeStruct::skip_field(self, key)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        ser::SerializeStruct::end(self)
+    }
 }